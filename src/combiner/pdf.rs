@@ -1,5 +1,19 @@
+pub mod attachment;
 pub mod combiner;
 pub mod controller;
+pub mod encrypt;
+pub mod geometry;
 pub mod location;
+pub mod merge;
+pub mod metadata;
+pub mod optimize;
+pub mod outline;
+pub mod overlay;
+pub mod page_label;
+pub mod pdfa;
+pub mod split;
+pub mod stamp;
 pub mod toc;
+pub mod verify;
+pub mod watermark;
 pub mod worker;
@@ -0,0 +1,221 @@
+use super::{
+    merge::{page_ids, text_string},
+    metadata::Timestamp,
+    overlay::VisualPage,
+};
+use crate::config::{
+    combine::PDFFile,
+    pdf::{AttachmentPlacement, AttachmentSetting},
+};
+use lopdf::{dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use md5::{Digest, Md5};
+use std::{collections::BTreeMap, fs, path::Path, time::UNIX_EPOCH};
+
+/// size of paperclip icon and its distance from top left corner of page
+const ICON_SIZE: f32 = 16.0;
+const ICON_MARGIN: f32 = 8.0;
+
+/// embed source rtf of outputs, as document level embedded files or file attachment annotations on the
+/// first page of every output, return the number of attached files
+///
+/// `page_actual` of files must be updated before attaching
+pub fn attach(
+    doc: &mut Document,
+    files: &[PDFFile],
+    setting: &AttachmentSetting,
+) -> anyhow::Result<usize> {
+    let pages = page_ids(doc);
+    let mut embedded = BTreeMap::new();
+    let mut attached = 0;
+    for file in files {
+        let source = match &file.source {
+            Some(source) if source.exists() => source,
+            _ => continue,
+        };
+        let name = source
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // names in name tree are unique, the first output wins
+        if setting.placement == AttachmentPlacement::Document && embedded.contains_key(&name) {
+            continue;
+        }
+        let filespec = filespec(
+            doc,
+            &name,
+            &file.title,
+            &fs::read(source)?,
+            modified(source),
+        )?;
+        match setting.placement {
+            AttachmentPlacement::Document => {
+                embedded.insert(name, filespec);
+            }
+            AttachmentPlacement::Annotation => match pages.get(&file.page_actual) {
+                Some(page_id) => annotate(doc, *page_id, filespec, &file.title)?,
+                None => continue,
+            },
+        }
+        attached += 1;
+    }
+    if setting.placement == AttachmentPlacement::Document && !embedded.is_empty() {
+        write_name_tree(doc, &embedded)?;
+    }
+    Ok(attached)
+}
+
+/// file specification with embedded file stream, size, modification date and checksum are recorded
+fn filespec(
+    doc: &mut Document,
+    name: &str,
+    description: &str,
+    content: &[u8],
+    modified: Option<Timestamp>,
+) -> anyhow::Result<ObjectId> {
+    let mut params = dictionary! {
+        "Size" => content.len() as i64,
+        "CheckSum" => Object::String(Md5::digest(content).to_vec(), StringFormat::Hexadecimal),
+    };
+    if let Some(modified) = modified {
+        params.set("ModDate", Object::string_literal(modified.pdf()));
+    }
+    let mut stream = Stream::new(
+        dictionary! {
+            "Type" => "EmbeddedFile",
+            "Subtype" => "application/rtf",
+            "Params" => params,
+        },
+        content.to_vec(),
+    );
+    stream.compress()?;
+    let file_id = doc.add_object(stream);
+    Ok(doc.add_object(dictionary! {
+        "Type" => "Filespec",
+        "F" => Object::string_literal(name),
+        "UF" => text_string(name),
+        "Desc" => text_string(description),
+        "AFRelationship" => "Source",
+        "EF" => dictionary! { "F" => file_id, "UF" => file_id },
+    }))
+}
+
+fn modified(path: &Path) -> Option<Timestamp> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let seconds = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Timestamp::from_unix(seconds))
+}
+
+/// paperclip at top left corner of page as it is displayed
+fn annotate(
+    doc: &mut Document,
+    page_id: ObjectId,
+    filespec: ObjectId,
+    title: &str,
+) -> anyhow::Result<()> {
+    let visual = VisualPage::new(doc, page_id)?;
+    let top = visual.height - ICON_MARGIN;
+    let (x0, y0) = visual.point(ICON_MARGIN, top - ICON_SIZE);
+    let (x1, y1) = visual.point(ICON_MARGIN + ICON_SIZE, top);
+    let annotation = doc.add_object(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "FileAttachment",
+        "Rect" => vec![x0.min(x1).into(), y0.min(y1).into(), x0.max(x1).into(), y0.max(y1).into()],
+        "FS" => filespec,
+        "Contents" => text_string(title),
+        "Name" => "Paperclip",
+        "P" => page_id,
+    });
+    let annots = doc.get_dictionary(page_id)?.get(b"Annots").ok().cloned();
+    match annots {
+        Some(Object::Reference(id)) => doc
+            .get_object_mut(id)?
+            .as_array_mut()?
+            .push(annotation.into()),
+        Some(Object::Array(mut array)) => {
+            array.push(annotation.into());
+            doc.get_dictionary_mut(page_id)?.set("Annots", array);
+        }
+        _ => doc
+            .get_dictionary_mut(page_id)?
+            .set("Annots", vec![annotation.into()]),
+    }
+    Ok(())
+}
+
+/// EmbeddedFiles name tree in Names of catalog, a single leaf node with names sorted
+fn write_name_tree(doc: &mut Document, files: &BTreeMap<String, ObjectId>) -> anyhow::Result<()> {
+    let names = files
+        .iter()
+        .flat_map(|(name, id)| [text_string(name), (*id).into()])
+        .collect::<Vec<Object>>();
+    let tree = doc.add_object(dictionary! { "Names" => names });
+    let names_dict = doc.catalog()?.get(b"Names").ok().cloned();
+    match names_dict {
+        Some(Object::Reference(id)) => {
+            doc.get_dictionary_mut(id)?.set("EmbeddedFiles", tree);
+        }
+        Some(Object::Dictionary(mut dict)) => {
+            dict.set("EmbeddedFiles", tree);
+            doc.catalog_mut()?.set("Names", dict);
+        }
+        _ => {
+            doc.catalog_mut()?
+                .set("Names", dictionary! { "EmbeddedFiles" => tree });
+        }
+    }
+    // show attachments panel when the document is opened
+    doc.catalog_mut()?.set("PageMode", "UseAttachments");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn attach_test() -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let page = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 842.into(), 595.into()],
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog);
+        let source = std::env::temp_dir().join("fusion_attach_test.rtf");
+        fs::write(&source, br"{\rtf1 Table 14.1.1}")?;
+        let files = vec![PDFFile {
+            title: "Table 14.1.1: Demographic".into(),
+            source: Some(source.clone()),
+            ..Default::default()
+        }];
+
+        assert_eq!(attach(&mut doc, &files, &AttachmentSetting::default())?, 1);
+        let names = doc.catalog()?.get(b"Names")?.as_dict()?;
+        let tree = doc.get_dictionary(names.get(b"EmbeddedFiles")?.as_reference()?)?;
+        assert_eq!(tree.get(b"Names")?.as_array()?.len(), 2);
+
+        let setting = AttachmentSetting {
+            placement: AttachmentPlacement::Annotation,
+            ..Default::default()
+        };
+        assert_eq!(attach(&mut doc, &files, &setting)?, 1);
+        let annotations = doc.get_page_annotations(page)?;
+        assert_eq!(
+            annotations[0].get(b"Subtype")?.as_name()?,
+            b"FileAttachment"
+        );
+        let rect = annotations[0].get(b"Rect")?.as_array()?;
+        assert_eq!(rect[3].as_float()?, 587.0);
+        fs::remove_file(&source)?;
+        Ok(())
+    }
+}
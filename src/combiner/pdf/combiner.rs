@@ -1,126 +1,402 @@
 use super::{
+    attachment::attach,
+    encrypt::encrypt,
+    geometry::normalize,
     location::{Location, LocationManager},
-    toc::render::{Render, ValidSize},
+    merge::{merge, page_ids},
+    metadata::{write_metadata, Metadata, Timestamp},
+    optimize::{compress, deduplicate, save_packed, saved_size},
+    outline::{outline_tree, toc_items, write_outline},
+    page_label::write_page_labels,
+    pdfa,
+    split::{link_volumes, volume_action, volumes},
+    stamp::stamp,
+    toc::{
+        export::TocExport,
+        native::{link, TocLink},
+        pool::BrowserPool,
+        render::Render,
+    },
+    verify::verify,
+    watermark::watermark,
+};
+use crate::config::{
+    combine::{CombinePDFParam, PDFFile},
+    pdf::{OptimizeSetting, PDFAConformance, SplitSetting},
 };
-use crate::config::combine::CombinePDFParam;
-use crate::config::utils::Language;
 use anyhow::anyhow;
 use lopdf::{dictionary, Document, Object, ObjectId};
-use serde::Serialize;
 use std::{
     collections::HashMap,
     fs,
-    os::windows::process::CommandExt,
     path::{Path, PathBuf},
-    process::Command,
+    sync::Arc,
 };
 
-const COVER_TITLE_EN: &str = "Cover";
-const COVER_TITLE_CN: &str = "封面";
-const TOC_TITLE_EN: &str = "Table of Content";
-const TOC_TITLE_CN: &str = "目录";
-
-#[derive(Debug, Serialize)]
-pub struct CombineConfig {
-    destination: PathBuf,
-    language: Language,
-    total: usize,
-    files: Vec<Location>,
-}
-
 pub struct PDFCombiner {
-    combine_bin: PathBuf,
     param: CombinePDFParam,
     location: LocationManager,
-    total_pages: usize,
+    logs: Vec<String>,
+    /// browser shared by the renders of a fusion run, renders launch their own browser if none
+    browsers: Option<Arc<BrowserPool>>,
 }
 
 impl PDFCombiner {
-    pub fn new(param: &CombinePDFParam, combine_bin: &Path) -> anyhow::Result<Self> {
-        let mut total_pages = 0;
+    pub fn new(
+        param: &CombinePDFParam,
+        browsers: &Option<Arc<BrowserPool>>,
+    ) -> anyhow::Result<Self> {
         let mut location: LocationManager = LocationManager::new();
         for file in &param.files {
+            if let Some(divider) = &file.divider {
+                render(param, browsers)?.print_divider(divider, &file.filepath)?;
+            }
             let doc = Document::load(&file.filepath)?;
             let pages = doc.get_pages().len();
-            location.push(Some(file.id), &file.title, pages, &file.filepath);
-            total_pages += pages;
+            match file.divider {
+                Some(_) => location.push_divider(Some(file.id), &file.title, pages, &file.filepath),
+                None => location.push(Some(file.id), &file.title, pages, &file.filepath),
+            };
+            if param.attachments.as_ref().is_some_and(|a| a.mark_toc)
+                && param.pdfa.is_none()
+                && file.source.as_ref().is_some_and(|s| s.exists())
+            {
+                location.mark_attachment();
+            }
+            if let Some(group) = &file.group {
+                location.set_group(group);
+            }
         }
         Ok(PDFCombiner {
             param: param.clone(),
             location,
-            combine_bin: combine_bin.into(),
-            total_pages,
+            logs: vec![],
+            browsers: browsers.clone(),
         })
     }
 
+    /// name of combined pdf without extension, which is the task name
+    fn name(&self) -> anyhow::Result<String> {
+        Ok(self
+            .param
+            .destination
+            .file_stem()
+            .ok_or(anyhow!(
+                "invalid destination {}",
+                self.param.destination.display()
+            ))?
+            .to_string_lossy()
+            .to_string())
+    }
+
+    /// file next to combined pdf named after it, such as "name_index.pdf" for suffix "_index.pdf"
+    fn sibling(&self, suffix: &str) -> anyhow::Result<PathBuf> {
+        Ok(self
+            .param
+            .destination
+            .with_file_name(format!("{}{}", self.name()?, suffix)))
+    }
+
+    /// messages collected during combining, such as PDF/A violations
+    pub fn logs(&self) -> Vec<String> {
+        self.logs.clone()
+    }
+
     pub fn combine(&mut self) -> anyhow::Result<()> {
-        self.create_toc()?;
-        self.combine_pdf()?;
-        self.rebuild_toc_links()?;
+        if self.param.security.is_some() && self.param.pdfa.is_some() {
+            return Err(anyhow!("PDF/A does not allow encryption"));
+        }
+        // overlays use fonts which are not embedded, and watermark is transparent
+        if (self.param.stamp.is_some() || self.param.watermark.is_some())
+            && self.param.pdfa.is_some()
+        {
+            return Err(anyhow!(
+                "PDF/A does not allow stamps or watermarks with fonts not embedded"
+            ));
+        }
+        if let Some(setting) = self.param.split.clone() {
+            let volumes = self.split_files(&setting)?;
+            if volumes.len() > 1 {
+                return self.combine_volumes(&volumes, &setting);
+            }
+        }
+        self.combine_single()
+    }
+
+    fn combine_single(&mut self) -> anyhow::Result<()> {
+        self.create_cover()?;
+        let items = self.toc_items();
+        let (toc_pages, toc_links) = self.create_toc(&items)?;
+        self.param.update_pages(toc_pages)?;
+        let mut doc = self.combine_pdf()?;
+        self.normalize_pages(&mut doc)?;
+        self.create_outline(&mut doc)?;
+        self.create_page_labels(&mut doc)?;
+        if let Some(setting) = &self.param.stamp {
+            stamp(&mut doc, &self.param.files, setting, &self.param.language)?;
+        }
+        if let Some(setting) = &self.param.watermark {
+            watermark(&mut doc, &self.param.files, setting, &self.param.language)?;
+        }
+        self.attach_sources(&mut doc)?;
+        self.rebuild_toc_links(&mut doc, &toc_links)?;
+        self.create_metadata(&mut doc)?;
+        if let Some(conformance) = self.param.pdfa.clone() {
+            self.conform_pdfa(&mut doc, &conformance)?;
+        }
+        let optimized = match self.param.optimize.clone() {
+            Some(setting) => Some(self.optimize(&mut doc, &setting)?),
+            None => None,
+        };
+        // text of encrypted document could not be extracted, so links are verified before encryption
+        self.verify(&doc)?;
+        if let Some(setting) = &self.param.security {
+            encrypt(&mut doc, setting)?;
+        }
+        self.save(&mut doc)?;
+        self.export_toc(&items)?;
+        if let Some((removed, before)) = optimized {
+            self.log_optimized(removed, before)?;
+        }
         Ok(())
     }
 
-    fn create_toc(&mut self) -> anyhow::Result<()> {
-        let mut render = Render::new()?;
-        render.set_content(match self.param.language {
-            Language::CN => TOC_TITLE_CN,
-            Language::EN => TOC_TITLE_EN,
-        });
-        render.set_size(match self.param.language {
-            Language::CN => &ValidSize::A4,
-            Language::EN => &ValidSize::LETTER,
-        });
-        render.set_toc_headers(&self.param.toc_headers);
-        render.print(&self.location.data(), &self.param.toc)?;
+    /// group files into volumes according to their pages and sizes
+    fn split_files(&self, setting: &SplitSetting) -> anyhow::Result<Vec<Vec<PDFFile>>> {
+        let outputs = self
+            .location
+            .pages()
+            .into_iter()
+            .zip(self.param.files.iter())
+            .map(|(pages, file)| Ok((pages, fs::metadata(&file.filepath)?.len())))
+            .collect::<anyhow::Result<Vec<(usize, u64)>>>()?;
+        Ok(volumes(&outputs, setting)
+            .into_iter()
+            .map(|volume| {
+                volume
+                    .into_iter()
+                    .map(|index| self.param.files[index].clone())
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// combine every volume into "name_partN.pdf" with its own toc and outline, then create the master index
+    fn combine_volumes(
+        &mut self,
+        volumes: &[Vec<PDFFile>],
+        setting: &SplitSetting,
+    ) -> anyhow::Result<()> {
+        let name = self.name()?;
+        let mut index = vec![];
+        let mut targets = HashMap::new();
+        let mut total_pages = 0;
+        for (part, files) in (1..).zip(volumes) {
+            let filename = format!("{}_part{}.pdf", name, part);
+            let mut param = self.param.clone();
+            param.files = files.clone();
+            param.toc = self.param.workspace.join(format!("toc_part{}.pdf", part));
+            param.destination = self.param.destination.with_file_name(&filename);
+            param.split = None;
+            let mut combiner = PDFCombiner::new(&param, &self.browsers)?;
+            combiner.combine_single()?;
+            self.logs.extend(combiner.logs());
+            let size = fs::metadata(&param.destination)?.len();
+            if setting.max_bytes().is_some_and(|max| size > max) {
+                self.logs.push(format!(
+                    "[WARN] {} exceeds max size, {:.1} MB",
+                    filename,
+                    size as f64 / 1024.0 / 1024.0
+                ));
+            }
+            let label = &self.param.catalog.volume_label;
+            // page numbers in toc count from the first output, same as toc of the volume
+            let body_start = combiner.param.files.first().map_or(0, |f| f.page_actual);
+            total_pages += combiner.location.total_pages() - body_start;
+            for location in combiner.location.data() {
+                if location.id.is_none() {
+                    continue;
+                }
+                // ids of outputs are unique across volumes only after renumbering
+                let id = index.len();
+                targets.insert(id.to_string(), (filename.clone(), location.page));
+                index.push(Location {
+                    id: Some(id),
+                    title: format!("[{} {}] {}", label, part, location.title),
+                    page: location.page - body_start,
+                    ..location
+                });
+            }
+        }
+        if setting.master_index {
+            self.create_index(&index, total_pages, &targets)?;
+        }
+        Ok(())
+    }
+
+    /// index of all volumes, saved as "name_index.pdf" next to volumes, entries show page numbers within volumes
+    fn create_index(
+        &self,
+        locations: &[Location],
+        total_pages: usize,
+        targets: &HashMap<String, (String, usize)>,
+    ) -> anyhow::Result<()> {
+        let source = self.param.workspace.join("index.pdf");
+        let (_, links) = self.render_toc(locations, total_pages, &source)?;
+        let mut doc = Document::load(&source)?;
+        link_volumes(&mut doc, targets)?;
+        let pages = page_ids(&doc).into_values().collect::<Vec<ObjectId>>();
+        link(&mut doc, &pages, &links, |output| {
+            targets
+                .get(&output.to_string())
+                .map(|(file, page)| dictionary! { "A" => volume_action(file, *page) })
+        })?;
+        if let Some(setting) = &self.param.security {
+            encrypt(&mut doc, setting)?;
+        }
+        doc.save(self.sibling("_index.pdf")?)?;
+        Ok(())
+    }
+
+    /// render cover page into workspace when the task has no cover pdf, a missing cover pdf is
+    /// reported before falling back to cover page, or dropped when there is no cover page
+    fn create_cover(&mut self) -> anyhow::Result<()> {
+        if let Some(cover) = self.param.cover.as_ref().filter(|cover| !cover.exists()) {
+            self.logs.push(format!(
+                "[WARN] {} cover {} not found, {}",
+                self.name()?,
+                cover.display(),
+                match self.param.cover_page {
+                    Some(_) => "render cover page instead",
+                    None => "combine without cover",
+                }
+            ));
+            self.param.cover = None;
+        }
+        let setting = match &self.param.cover_page {
+            Some(setting) if self.param.cover.is_none() => setting,
+            _ => return Ok(()),
+        };
+        let render = render(&self.param, &self.browsers)?;
+        let cover = self.param.workspace.join("cover.pdf");
+        render.print_cover(
+            setting,
+            &self.param.catalog.cover_labels,
+            &Timestamp::now().date(),
+            &cover,
+        )?;
+        self.param.cover = Some(cover);
         Ok(())
     }
 
-    fn combine_pdf(&mut self) -> anyhow::Result<()> {
+    /// entries of toc, outputs with headings when toc has levels
+    fn toc_items(&self) -> Vec<Location> {
+        let locations = self.location.data();
+        match &self.param.toc_levels {
+            Some(setting) => {
+                let tree = outline_tree(&locations, &self.param.outline, &self.param.catalog);
+                toc_items(&locations, &tree, setting, self.location.total_pages())
+            }
+            None => locations,
+        }
+    }
+
+    /// render toc of outputs, return the number of toc pages and links of native toc
+    fn create_toc(&self, items: &[Location]) -> anyhow::Result<(usize, Vec<TocLink>)> {
+        self.render_toc(items, self.location.total_pages(), &self.param.toc)
+    }
+
+    /// export toc next to combined pdf, such as "name_toc.docx"
+    fn export_toc(&mut self, items: &[Location]) -> anyhow::Result<()> {
+        if self.param.toc_exports.is_empty() {
+            return Ok(());
+        }
+        let mut export = TocExport::new(items);
+        export
+            .set_title(&self.param.catalog.toc_title)
+            .set_toc_headers(&self.param.toc_headers)
+            .set_catalog(&self.param.catalog);
+        match &self.param.page {
+            Some(setting) => export.set_size(&setting.size),
+            None => export.set_size(&self.param.catalog.paper),
+        };
+        if let Some(setting) = &self.param.toc_levels {
+            export.set_indent(setting.indent);
+        }
+        for format in &self.param.toc_exports {
+            let dest = self.sibling(&format!("_toc.{}", format.extension()))?;
+            export.save(format, &dest)?;
+            self.logs
+                .push(format!("[INFO] toc exported to {}", dest.display()));
+        }
+        Ok(())
+    }
+
+    /// `total_pages` is pages of outputs listed in toc
+    fn render_toc(
+        &self,
+        locations: &[Location],
+        total_pages: usize,
+        destination: &Path,
+    ) -> anyhow::Result<(usize, Vec<TocLink>)> {
+        let mut render = render(&self.param, &self.browsers)?;
+        render.set_total_pages(total_pages);
+        if let Some(setting) = &self.param.toc_levels {
+            render.set_indent(setting.indent);
+        }
+        render.set_content(&self.param.catalog.toc_title);
+        render.set_toc_headers(&self.param.toc_headers);
+        render.print(locations, destination)
+    }
+
+    /// merge cover, toc and outputs into one document
+    fn combine_pdf(&mut self) -> anyhow::Result<Document> {
         // toc
         if self.param.toc.exists() {
             let toc = self.param.toc.as_path();
-            let title: String = match self.param.language {
-                Language::CN => TOC_TITLE_CN.into(),
-                Language::EN => TOC_TITLE_EN.into(),
-            };
             let page = Document::load(toc)?.get_pages().len();
-            self.location.insert_head(None, &title, page, toc);
+            self.location
+                .insert_head(None, &self.param.catalog.toc_title, page, toc);
         }
         // cover
         if let Some(cover) = &self.param.cover {
             if cover.exists() {
-                let title: String = match self.param.language {
-                    Language::CN => COVER_TITLE_CN.into(),
-                    Language::EN => COVER_TITLE_EN.into(),
-                };
-                let page = Document::load(&cover)?.get_pages().len();
-                self.location.insert_head(None, &title, page, &cover);
+                let page = Document::load(cover)?.get_pages().len();
+                self.location
+                    .insert_head(None, &self.param.catalog.cover_title, page, cover);
             }
         }
+        merge(&self.location.data())
+    }
 
-        let config = self.param.workspace.join("config.json");
-        fs::write(
-            &config,
-            serde_json::to_vec(&CombineConfig {
-                destination: self.param.destination.clone(),
-                language: self.param.language.clone(),
-                files: self.location.data(),
-                total: self.total_pages,
-            })?,
-        )?;
-        let mut cmd = Command::new("cmd");
-        cmd.creation_flags(0x08000000);
-        // call binary to combine pdf and add outline
-        let result = cmd.arg("/C").arg(&self.combine_bin).arg(&config).output()?;
-        if !result.status.success() {
-            let err_message = result.stderr;
-            return Err(anyhow!(String::from_utf8(err_message)?));
-        }
-        Ok(())
+    fn create_outline(&self, doc: &mut Document) -> anyhow::Result<()> {
+        let tree = outline_tree(
+            &self.location.data(),
+            &self.param.outline,
+            &self.param.catalog,
+        );
+        write_outline(doc, &tree)
+    }
+
+    fn create_page_labels(&self, doc: &mut Document) -> anyhow::Result<()> {
+        let cover_pages = self.param.toc_start_pages;
+        let body_start = match self.param.files.first() {
+            Some(file) => file.page_actual,
+            None => doc.get_pages().len(),
+        };
+        write_page_labels(
+            doc,
+            cover_pages,
+            body_start - cover_pages,
+            &self.param.catalog.cover_title,
+        )
     }
-    /// rebuild links in toc according combine parameters
-    fn rebuild_toc_links(&self) -> anyhow::Result<()> {
-        let mut doc = Document::load(&self.param.destination)?;
+
+    /// rebuild links in toc according combine parameters, destinations which are not ids of outputs
+    /// are left untouched and reported as orphaned by verification, links of native toc are added to
+    /// toc pages with destinations of output pages
+    fn rebuild_toc_links(&self, doc: &mut Document, links: &[TocLink]) -> anyhow::Result<()> {
+        let pages = page_ids(doc);
         let outputs = self
             .location
             .data()
@@ -136,58 +412,349 @@ impl PDFCombiner {
         for id in obj_ids {
             let obj = doc.get_object_mut(id)?;
             if let Ok(obj) = obj.as_dict_mut() {
-                if obj.type_is(b"Annot") {
-                    if let Ok(dest) = obj.get(b"Dest")?.as_name_str() {
-                        let id = dest.to_string().parse::<usize>()?;
+                if obj.has_type(b"Annot") {
+                    let id = obj
+                        .get(b"Dest")
+                        .and_then(Object::as_name)
+                        .ok()
+                        .and_then(|dest| String::from_utf8_lossy(dest).parse::<usize>().ok());
+                    if let Some(id) = id {
                         if let Some(page) = outputs.get(&id) {
-                            obj.set(
-                                b"Dest",
-                                Object::Array(vec![
-                                    Object::Integer(*page as i64),
-                                    Object::Name(b"XYZ".into()),
-                                    Object::Null,
-                                    Object::Null,
-                                    Object::Null,
-                                    Object::Dictionary(dictionary! {
-                                        "XYZ" => vec![Object::Null, Object::Null, Object::Null]
-                                    }),
-                                ]),
-                            );
+                            let page = pages
+                                .get(page)
+                                .ok_or(anyhow!("page {} not found in combined pdf", page))?;
+                            obj.set(b"Dest", destination(*page));
                         }
                     }
                 }
             }
         }
-        doc.save(&self.param.destination)?;
+        let toc_start = self
+            .location
+            .data()
+            .into_iter()
+            .find(|l| l.id.is_none() && l.path == self.param.toc)
+            .map(|l| l.page);
+        if let Some(start) = toc_start {
+            let toc_pages = pages.range(start..).map(|(_, id)| *id).collect::<Vec<_>>();
+            link(doc, &toc_pages, links, |output| {
+                outputs
+                    .get(&output)
+                    .and_then(|page| pages.get(page))
+                    .map(|page| dictionary! { "Dest" => destination(*page) })
+            })?;
+        }
         Ok(())
     }
+
+    /// verify links and bookmarks, report is saved as "name.verify.json" next to destination
+    fn verify(&mut self, doc: &Document) -> anyhow::Result<()> {
+        let report = verify(doc, &self.location.data(), self.location.total_pages())?;
+        let name = self.name()?;
+        fs::write(
+            self.sibling(".verify.json")?,
+            serde_json::to_string_pretty(&report)?,
+        )?;
+        if !report.passed {
+            self.logs.push(format!(
+                "[WARN] {} verification failed, {} of {} pages, {} broken links or bookmarks",
+                name,
+                report.pages.actual,
+                report.pages.expected,
+                report.failures()
+            ));
+        }
+        Ok(())
+    }
+
+    /// write document information, title defaults to name of destination, which is the task name
+    fn create_metadata(&self, doc: &mut Document) -> anyhow::Result<()> {
+        let setting = &self.param.metadata;
+        let name = self.name()?;
+        let producer = format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        write_metadata(
+            doc,
+            &Metadata {
+                title: setting.title.clone().unwrap_or(name),
+                author: setting.author.clone(),
+                subject: setting.subject.clone(),
+                keywords: setting.keywords.clone(),
+                creator: setting
+                    .creator
+                    .clone()
+                    .unwrap_or(env!("CARGO_PKG_NAME").into()),
+                producer,
+                language: self.param.catalog.tag.clone(),
+                created: Timestamp::now(),
+                properties: setting.properties.clone(),
+                pdfa: self.param.pdfa.as_ref().map(|c| c.identification()),
+            },
+        )
+    }
+
+    /// normalize pages to the paper of page setting, scaled pages are logged
+    fn normalize_pages(&mut self, doc: &mut Document) -> anyhow::Result<()> {
+        let setting = match &self.param.page {
+            Some(setting) => setting,
+            None => return Ok(()),
+        };
+        let name = self.name()?;
+        for (index, scale) in normalize(doc, setting)? {
+            self.logs.push(format!(
+                "[INFO] {} page {} scaled to {:.1}%",
+                name,
+                index + 1,
+                scale * 100.0
+            ));
+        }
+        Ok(())
+    }
+
+    /// embed source rtf files, skipped for PDF/A which does not allow embedding non PDF/A files
+    fn attach_sources(&mut self, doc: &mut Document) -> anyhow::Result<()> {
+        let setting = match &self.param.attachments {
+            Some(setting) => setting,
+            None => return Ok(()),
+        };
+        let name = self.name()?;
+        if self.param.pdfa.is_some() {
+            self.logs.push(format!(
+                "[WARN] {} source files are not attached, PDF/A does not allow embedding them",
+                name
+            ));
+            return Ok(());
+        }
+        let attached = attach(doc, &self.param.files, setting)?;
+        self.logs.push(format!(
+            "[INFO] {} {} source files attached",
+            name, attached
+        ));
+        Ok(())
+    }
+
+    /// share identical fonts and images and compress streams,
+    /// return the number of removed objects and the size of combined pdf before optimizing
+    fn optimize(
+        &mut self,
+        doc: &mut Document,
+        setting: &OptimizeSetting,
+    ) -> anyhow::Result<(usize, u64)> {
+        let before = saved_size(doc)?;
+        let removed = if setting.deduplicate {
+            deduplicate(doc)?
+        } else {
+            0
+        };
+        if setting.compress {
+            compress(doc);
+        }
+        Ok((removed, before))
+    }
+
+    /// save combined pdf, object streams are not used for PDF/A-1 or encrypted documents
+    fn save(&self, doc: &mut Document) -> anyhow::Result<()> {
+        let object_streams = self
+            .param
+            .optimize
+            .as_ref()
+            .is_some_and(|setting| setting.object_streams);
+        if object_streams
+            && self.param.pdfa != Some(PDFAConformance::A1B)
+            && self.param.security.is_none()
+        {
+            save_packed(doc, &self.param.destination)?;
+        } else {
+            doc.save(&self.param.destination)?;
+        }
+        Ok(())
+    }
+
+    /// log sizes of combined pdf before optimizing and as saved
+    fn log_optimized(&mut self, removed: usize, before: u64) -> anyhow::Result<()> {
+        let name = self.name()?;
+        let after = fs::metadata(&self.param.destination)?.len();
+        self.logs.push(format!(
+            "[INFO] {} optimized, {} duplicated objects removed, {:.1} MB to {:.1} MB",
+            name,
+            removed,
+            before as f64 / 1024.0 / 1024.0,
+            after as f64 / 1024.0 / 1024.0
+        ));
+        Ok(())
+    }
+
+    /// convert to PDF/A, violations could not be fixed are logged as warnings
+    fn conform_pdfa(
+        &mut self,
+        doc: &mut Document,
+        conformance: &PDFAConformance,
+    ) -> anyhow::Result<()> {
+        let name = self.name()?;
+        for violation in pdfa::convert(doc, conformance)? {
+            self.logs
+                .push(format!("[WARN] {} PDF/A violation: {}", name, violation));
+        }
+        Ok(())
+    }
+}
+
+/// destination of the top of page
+fn destination(page: ObjectId) -> Object {
+    Object::Array(vec![
+        Object::Reference(page),
+        Object::Name(b"XYZ".into()),
+        Object::Null,
+        Object::Null,
+        Object::Null,
+    ])
+}
+
+/// render of toc and generated pages, paper follows page setting, otherwise A4 for CN and Letter for EN
+fn render(param: &CombinePDFParam, browsers: &Option<Arc<BrowserPool>>) -> anyhow::Result<Render> {
+    let mut render = Render::new()?;
+    let toc = &param.toc_template;
+    render
+        .set_backend(&param.toc_backend)
+        .set_browsers(browsers)
+        .set_language(&param.language)
+        .set_study(&toc.study)
+        .set_company(&toc.company)
+        .set_purpose(&toc.purpose)
+        .set_font(&toc.font);
+    if let Some(path) = &toc.path {
+        render.set_template(path)?;
+    }
+    match &param.page {
+        Some(setting) => render
+            .set_size(&(&setting.size).into())
+            .set_orientation(&setting.orientation),
+        None => render.set_size(&(&param.catalog.paper).into()),
+    };
+    Ok(render)
 }
 
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
+    use super::*;
+    use crate::{
+        combiner::pdf::toc::font::tests::test_font,
+        config::{
+            combine::PDFFile,
+            pdf::{RenderBackend, TocFormat, TocTemplate},
+            utils::Language,
+        },
+    };
+    use lopdf::Stream;
 
-    use crate::{config::combine::PDFFile, top::read_top};
+    /// pdf of one page showing title, so that verification finds title on the page
+    fn write_output(path: &Path, title: &str) -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let content = format!("BT /F1 12 Tf 72 720 Td ({}) Tj ET", title);
+        let contents = doc.add_object(Stream::new(dictionary! {}, content.into_bytes()));
+        let page = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => contents,
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font } },
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page.into()],
+                "Count" => 1,
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            }),
+        );
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog);
+        doc.save(path)?;
+        Ok(())
+    }
 
-    use super::*;
+    #[test]
+    fn combine_test() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join("fusion_pdf_combine");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        let font = dir.join("toc.ttf");
+        fs::write(&font, test_font())?;
+        let files = ["Table 14.1.1: Demographic", "Table 14.3.1: Adverse Events"]
+            .iter()
+            .enumerate()
+            .map(|(id, title)| {
+                let filepath = dir.join(format!("output{}.pdf", id));
+                write_output(&filepath, title)?;
+                Ok(PDFFile {
+                    id,
+                    title: title.to_string(),
+                    filepath,
+                    ..Default::default()
+                })
+            })
+            .collect::<anyhow::Result<Vec<PDFFile>>>()?;
+        let workspace = dir.join("workspace");
+        let mut param = CombinePDFParam::new(
+            &workspace,
+            &Language::EN,
+            &Some(dir.join("cover.pdf")),
+            &workspace.join("toc.pdf"),
+            &files,
+            &dir.join("final.pdf"),
+            &Default::default(),
+        )?;
+        param
+            .set_toc_backend(&RenderBackend::Native)
+            .set_toc_exports(&[TocFormat::HTML])
+            .set_toc_template(&TocTemplate {
+                font: Some(font),
+                ..Default::default()
+            });
+        let mut combiner = PDFCombiner::new(&param, &None)?;
+        combiner.combine()?;
+
+        // missing cover is dropped, toc takes one page before outputs
+        let doc = Document::load(dir.join("final.pdf"))?;
+        assert_eq!(doc.get_pages().len(), 3);
+        let logs = combiner.logs();
+        assert!(logs[0].contains("combine without cover"));
+        assert!(!logs.iter().any(|log| log.contains("verification failed")));
+        assert!(dir.join("final_toc.html").exists());
+        let report: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("final.verify.json"))?)?;
+        assert_eq!(report["links"].as_array().map(Vec::len), Some(2));
+        Ok(())
+    }
+
+    // fixtures are on a windows drive
+    #[cfg(windows)]
     #[test]
     fn combine_pdf_test() -> anyhow::Result<()> {
-        let bin = Path::new(r"D:\projects\py\combiner\dist\combiner.exe");
         let param: CombinePDFParam = param();
-        let mut combiner = PDFCombiner::new(&param, bin)?;
+        let mut combiner = PDFCombiner::new(&param, &None)?;
         combiner.combine()?;
         Ok(())
     }
 
+    #[cfg(windows)]
     fn param() -> CombinePDFParam {
         let source_dir = Path::new(
             r"D:\Users\yuqi01.chen\.temp\app\mobiuskit\fusion\workspace\MU0LrjDeuu\converted",
         );
         let destination =
             Path::new(r"D:\Studies\ak101\203\stats\dryrun\product\output\combined\final.pdf");
-        let files = read_top(Path::new(
-            r"D:\Studies\ak102\202\stats\idmc\utility\top-ak112-101-20240620.xlsx",
-        ))
+        let files = crate::top::read_top(
+            Path::new(r"D:\Studies\ak102\202\stats\idmc\utility\top-ak112-101-20240620.xlsx"),
+            &Language::EN.catalog(),
+        )
         .unwrap()
         .into_iter()
         .enumerate()
@@ -215,6 +782,22 @@ mod tests {
             files,
             destination: destination.into(),
             toc_headers: ("".into(), "".into(), "".into(), "".into()),
+            outline: Default::default(),
+            stamp: None,
+            watermark: None,
+            pdfa: None,
+            split: None,
+            metadata: Default::default(),
+            optimize: None,
+            cover_page: None,
+            security: None,
+            attachments: None,
+            page: None,
+            toc_backend: Default::default(),
+            toc_template: Default::default(),
+            toc_levels: None,
+            toc_exports: vec![],
+            catalog: Language::EN.catalog(),
         }
     }
 }
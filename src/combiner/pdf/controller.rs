@@ -1,11 +1,8 @@
-use std::{
-    path::Path,
-    sync::{mpsc, Arc, Mutex},
-};
+use std::sync::{mpsc, Arc, Mutex};
 
 use crate::config::combine::CombinePDFParam;
 
-use super::worker::PDFCombineWorker;
+use super::{toc::pool::BrowserPool, worker::PDFCombineWorker};
 
 pub struct PDFCombineController {
     workers: Vec<PDFCombineWorker>,
@@ -15,18 +12,21 @@ pub struct PDFCombineController {
 impl PDFCombineController {
     pub fn new(
         worker_number: usize,
+        browser_tabs: usize,
         status: Arc<Mutex<mpsc::Sender<()>>>,
         logger: Arc<Mutex<mpsc::Sender<String>>>,
-        bin: &Path,
     ) -> Self {
         let (tx, rx) = mpsc::channel();
         let rx = Arc::new(Mutex::new(rx));
+        // one browser prints toc, cover and dividers of all pdf tasks
+        let browsers = Arc::new(BrowserPool::new(browser_tabs, Arc::clone(&logger)));
         let mut workers = Vec::with_capacity(worker_number);
         for id in 0..worker_number {
             let rx = Arc::clone(&rx);
+            let browsers = Arc::clone(&browsers);
             let status = Arc::clone(&status);
             let logger = Arc::clone(&logger);
-            workers.push(PDFCombineWorker::new(id, bin, rx, status, logger));
+            workers.push(PDFCombineWorker::new(id, rx, browsers, status, logger));
         }
         PDFCombineController {
             workers,
@@ -0,0 +1,370 @@
+use super::metadata::require_version;
+use crate::config::pdf::{EncryptionAlgorithm, SecuritySetting};
+use aes::{Aes128, Aes256};
+use cbc::{
+    cipher::{
+        block_padding::{NoPadding, Padding, Pkcs7},
+        consts::U16,
+        BlockEncryptMut, KeyInit, KeyIvInit, StreamCipher,
+    },
+    Encryptor,
+};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, StringFormat};
+use md5::Md5;
+use rand::RngCore;
+use rc4::Rc4;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/// padding of passwords for revision 4 security handler
+const PADDING: [u8; 32] = [
+    0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08,
+    0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+/// passwords of revision 6 are truncated to 127 bytes of utf-8
+const MAX_PASSWORD_LENGTH: usize = 127;
+
+/// encrypt strings and streams with standard security handler, AES-128 is revision 4 and AES-256 is revision 6,
+/// document is expected to be saved without object streams afterwards
+pub fn encrypt(doc: &mut Document, setting: &SecuritySetting) -> anyhow::Result<()> {
+    if doc.trailer.has(b"Encrypt") {
+        anyhow::bail!("document is already encrypted");
+    }
+    let owner = match setting.owner_password.is_empty() {
+        true => random_bytes(16)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>(),
+        false => setting.owner_password.clone(),
+    };
+    let permissions = setting.permissions.flags();
+    let file_id = file_id(doc);
+    let (key, dict) = match setting.algorithm {
+        EncryptionAlgorithm::AES128 => standard_r4(
+            owner.as_bytes(),
+            setting.user_password.as_bytes(),
+            permissions,
+            &file_id,
+        ),
+        EncryptionAlgorithm::AES256 => standard_r6(
+            owner.as_bytes(),
+            setting.user_password.as_bytes(),
+            permissions,
+        ),
+    };
+    for (id, object) in doc.objects.iter_mut() {
+        // cross reference streams are never encrypted
+        if let Object::Stream(stream) = object {
+            if stream.dict.has_type(b"XRef") {
+                continue;
+            }
+        }
+        let object_key = match setting.algorithm {
+            EncryptionAlgorithm::AES128 => object_key(&key, *id),
+            EncryptionAlgorithm::AES256 => key.clone(),
+        };
+        encrypt_object(object, &object_key);
+    }
+    let encrypt_id = doc.add_object(dict);
+    doc.trailer.set("Encrypt", encrypt_id);
+    let version = match setting.algorithm {
+        EncryptionAlgorithm::AES128 => "1.6",
+        EncryptionAlgorithm::AES256 => "2.0",
+    };
+    require_version(doc, version);
+    Ok(())
+}
+
+fn encrypt_object(object: &mut Object, key: &[u8]) {
+    match object {
+        Object::String(bytes, format) => {
+            *bytes = aes_encrypt(key, bytes);
+            *format = StringFormat::Hexadecimal;
+        }
+        Object::Array(array) => array.iter_mut().for_each(|item| encrypt_object(item, key)),
+        Object::Dictionary(dict) => encrypt_dictionary(dict, key),
+        Object::Stream(stream) => {
+            encrypt_dictionary(&mut stream.dict, key);
+            let content = aes_encrypt(key, &stream.content);
+            stream.set_content(content);
+        }
+        _ => {}
+    }
+}
+
+fn encrypt_dictionary(dict: &mut Dictionary, key: &[u8]) {
+    dict.iter_mut()
+        .for_each(|(_, value)| encrypt_object(value, key));
+}
+
+/// first string of file identifier, which is created when missing
+fn file_id(doc: &mut Document) -> Vec<u8> {
+    if let Ok(id) = doc
+        .trailer
+        .get(b"ID")
+        .and_then(Object::as_array)
+        .and_then(|ids| ids.first().ok_or(lopdf::Error::DictKey("ID".into())))
+        .and_then(Object::as_str)
+    {
+        return id.to_vec();
+    }
+    let id = random_bytes(16);
+    doc.trailer.set(
+        "ID",
+        vec![
+            Object::String(id.clone(), StringFormat::Hexadecimal),
+            Object::String(id.clone(), StringFormat::Hexadecimal),
+        ],
+    );
+    id
+}
+
+/// file key and encryption dictionary of revision 4 with AESV2 crypt filter
+fn standard_r4(
+    owner: &[u8],
+    user: &[u8],
+    permissions: i32,
+    file_id: &[u8],
+) -> (Vec<u8>, Dictionary) {
+    // owner entry, user password encrypted by key from owner password
+    let mut hash = Md5::digest(pad_password(owner)).to_vec();
+    for _ in 0..50 {
+        hash = Md5::digest(&hash).to_vec();
+    }
+    let o = rc4_rounds(&hash[0..16], &pad_password(user));
+
+    // file key from user password
+    let mut hasher = Md5::new();
+    hasher.update(pad_password(user));
+    hasher.update(&o);
+    hasher.update(permissions.to_le_bytes());
+    hasher.update(file_id);
+    let mut key = hasher.finalize().to_vec();
+    for _ in 0..50 {
+        key = Md5::digest(&key[0..16]).to_vec();
+    }
+    key.truncate(16);
+
+    // user entry, padding and file identifier encrypted by file key, followed by arbitrary bytes
+    let mut hasher = Md5::new();
+    hasher.update(PADDING);
+    hasher.update(file_id);
+    let mut u = rc4_rounds(&key, &hasher.finalize());
+    u.extend([0; 16]);
+
+    let dict = dictionary! {
+        "Filter" => "Standard",
+        "V" => 4,
+        "R" => 4,
+        "Length" => 128,
+        "CF" => dictionary! {
+            "StdCF" => dictionary! {
+                "Type" => "CryptFilter",
+                "CFM" => "AESV2",
+                "AuthEvent" => "DocOpen",
+                "Length" => 16,
+            },
+        },
+        "StmF" => "StdCF",
+        "StrF" => "StdCF",
+        "O" => Object::String(o, StringFormat::Hexadecimal),
+        "U" => Object::String(u, StringFormat::Hexadecimal),
+        "P" => permissions,
+        "EncryptMetadata" => true,
+    };
+    (key, dict)
+}
+
+/// random file key and encryption dictionary of revision 6 with AESV3 crypt filter
+fn standard_r6(owner: &[u8], user: &[u8], permissions: i32) -> (Vec<u8>, Dictionary) {
+    let key = random_bytes(32);
+    let owner = &owner[..owner.len().min(MAX_PASSWORD_LENGTH)];
+    let user = &user[..user.len().min(MAX_PASSWORD_LENGTH)];
+
+    // validation salt and key salt follow the hash
+    let salts = random_bytes(16);
+    let mut u = hash_r6(user, &salts[0..8], &[]).to_vec();
+    u.extend(&salts);
+    let ue = aes_cbc::<NoPadding>(&hash_r6(user, &salts[8..16], &[]), &[0; 16], &key);
+
+    let salts = random_bytes(16);
+    let mut o = hash_r6(owner, &salts[0..8], &u).to_vec();
+    o.extend(&salts);
+    let oe = aes_cbc::<NoPadding>(&hash_r6(owner, &salts[8..16], &u), &[0; 16], &key);
+
+    let mut perms = permissions.to_le_bytes().to_vec();
+    perms.extend([0xFF; 4]);
+    perms.extend(b"Tadb");
+    perms.extend(random_bytes(4));
+    let perms = aes_cbc::<NoPadding>(&key, &[0; 16], &perms);
+
+    let dict = dictionary! {
+        "Filter" => "Standard",
+        "V" => 5,
+        "R" => 6,
+        "Length" => 256,
+        "CF" => dictionary! {
+            "StdCF" => dictionary! {
+                "Type" => "CryptFilter",
+                "CFM" => "AESV3",
+                "AuthEvent" => "DocOpen",
+                "Length" => 32,
+            },
+        },
+        "StmF" => "StdCF",
+        "StrF" => "StdCF",
+        "O" => Object::String(o, StringFormat::Hexadecimal),
+        "U" => Object::String(u, StringFormat::Hexadecimal),
+        "OE" => Object::String(oe, StringFormat::Hexadecimal),
+        "UE" => Object::String(ue, StringFormat::Hexadecimal),
+        "Perms" => Object::String(perms, StringFormat::Hexadecimal),
+        "P" => permissions,
+        "EncryptMetadata" => true,
+    };
+    (key, dict)
+}
+
+/// hash of revision 6 passwords, rounds of AES-128 and SHA-2 until the last byte meets the round number
+fn hash_r6(password: &[u8], salt: &[u8], user_key: &[u8]) -> [u8; 32] {
+    let mut k = Sha256::digest([password, salt, user_key].concat()).to_vec();
+    let mut round = 0;
+    loop {
+        let k1 = [password, &k, user_key].concat().repeat(64);
+        let e = aes_cbc::<NoPadding>(&k[0..16], &k[16..32], &k1);
+        k = match e[0..16].iter().map(|b| *b as u32).sum::<u32>() % 3 {
+            0 => Sha256::digest(&e).to_vec(),
+            1 => Sha384::digest(&e).to_vec(),
+            _ => Sha512::digest(&e).to_vec(),
+        };
+        round += 1;
+        if round >= 64 && (*e.last().unwrap() as usize) + 32 <= round {
+            break;
+        }
+    }
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&k[0..32]);
+    hash
+}
+
+/// key of an object for AESV2, derived from file key, object number and generation
+fn object_key(key: &[u8], id: ObjectId) -> Vec<u8> {
+    let mut hasher = Md5::new();
+    hasher.update(key);
+    hasher.update(&id.0.to_le_bytes()[0..3]);
+    hasher.update(id.1.to_le_bytes());
+    hasher.update(b"sAlT");
+    hasher.finalize().to_vec()
+}
+
+fn pad_password(password: &[u8]) -> Vec<u8> {
+    password
+        .iter()
+        .chain(PADDING.iter())
+        .take(32)
+        .copied()
+        .collect()
+}
+
+/// RC4 with the key, then 19 more times with the key xor round number
+fn rc4_rounds(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut data = rc4(key, data);
+    for round in 1..=19u8 {
+        let key = key.iter().map(|b| b ^ round).collect::<Vec<u8>>();
+        data = rc4(&key, &data);
+    }
+    data
+}
+
+/// RC4 with a key of 16 bytes
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    Rc4::<U16>::new_from_slice(key)
+        .expect("RC4 key of 16 bytes")
+        .apply_keystream(&mut data);
+    data
+}
+
+/// random initialization vector followed by data encrypted in CBC mode with PKCS#7 padding
+fn aes_encrypt(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let iv = random_bytes(16);
+    let mut result = iv.clone();
+    result.extend(aes_cbc::<Pkcs7>(key, &iv, data));
+    result
+}
+
+/// CBC mode with key of 16 or 32 bytes, data must be a multiple of 16 bytes without padding
+fn aes_cbc<P: Padding<U16>>(key: &[u8], iv: &[u8], data: &[u8]) -> Vec<u8> {
+    match key.len() {
+        16 => Encryptor::<Aes128>::new_from_slices(key, iv)
+            .map(|cipher| cipher.encrypt_padded_vec_mut::<P>(data)),
+        _ => Encryptor::<Aes256>::new_from_slices(key, iv)
+            .map(|cipher| cipher.encrypt_padded_vec_mut::<P>(data)),
+    }
+    .expect("AES key of 16 or 32 bytes and iv of 16 bytes")
+}
+
+fn random_bytes(length: usize) -> Vec<u8> {
+    let mut bytes = vec![0; length];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::pdf::Permissions;
+    use lopdf::Stream;
+
+    #[test]
+    fn encrypt_test() -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.4");
+        let info = doc.add_object(dictionary! { "Title" => Object::string_literal("Tables") });
+        doc.trailer.set("Info", info);
+        let setting = SecuritySetting {
+            owner_password: "owner".into(),
+            user_password: "user".into(),
+            algorithm: EncryptionAlgorithm::AES256,
+            permissions: Permissions {
+                print: true,
+                ..Default::default()
+            },
+        };
+        encrypt(&mut doc, &setting)?;
+        assert_eq!(doc.version, "2.0");
+        let encrypt_dict = doc.get_dictionary(doc.trailer.get(b"Encrypt")?.as_reference()?)?;
+        assert_eq!(encrypt_dict.get(b"P")?.as_i64()?, -1340);
+        // user password validates against hash and validation salt of U
+        let u = encrypt_dict.get(b"U")?.as_str()?;
+        assert_eq!(hash_r6(b"user", &u[32..40], &[]).as_slice(), &u[0..32]);
+        // title is encrypted with a random iv
+        let title = doc.get_dictionary(info)?.get(b"Title")?.as_str()?;
+        assert_eq!(title.len(), 32);
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip_test() -> anyhow::Result<()> {
+        for algorithm in [EncryptionAlgorithm::AES128, EncryptionAlgorithm::AES256] {
+            let mut doc = Document::with_version("1.4");
+            let info = doc.add_object(dictionary! { "Title" => Object::string_literal("Tables") });
+            doc.trailer.set("Info", info);
+            let content = b"BT /F1 12 Tf (Table 14.1.1) Tj ET".to_vec();
+            let stream = doc.add_object(Stream::new(dictionary! {}, content.clone()));
+            let setting = SecuritySetting {
+                owner_password: "owner".into(),
+                user_password: "user".into(),
+                algorithm,
+                permissions: Default::default(),
+            };
+            encrypt(&mut doc, &setting)?;
+            assert_ne!(doc.get_object(stream)?.as_stream()?.content, content);
+            doc.authenticate_user_password("user")?;
+            doc.authenticate_owner_password("owner")?;
+            assert!(doc.authenticate_password("other").is_err());
+            doc.decrypt("user")?;
+            let title = doc.get_dictionary(info)?.get(b"Title")?.as_str()?;
+            assert_eq!(title, b"Tables");
+            assert_eq!(doc.get_object(stream)?.as_stream()?.content, content);
+        }
+        Ok(())
+    }
+}
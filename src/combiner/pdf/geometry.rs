@@ -0,0 +1,217 @@
+use super::{merge::page_ids, overlay::VisualPage};
+use crate::config::pdf::{FitPolicy, PageOrientation, PageSetting};
+use lopdf::{
+    content::{Content, Operation},
+    Dictionary, Document, Object, ObjectId, Stream,
+};
+
+/// pages differing from the paper by less than this ratio are centred without scaling,
+/// such as 595 x 842 pages on A4 paper of 595.28 x 841.89
+const SCALE_TOLERANCE: f32 = 0.01;
+/// page boxes removed after normalizing, they are relative to the original MediaBox
+const PAGE_BOXES: [&[u8]; 3] = [b"BleedBox", b"TrimBox", b"ArtBox"];
+
+/// normalize MediaBox, CropBox and rotation of every page to the paper, content and annotations are
+/// transformed onto the paper, return index and scale of pages which are scaled
+pub fn normalize(doc: &mut Document, setting: &PageSetting) -> anyhow::Result<Vec<(usize, f32)>> {
+    let (width, height) = paper(setting);
+    let mut scaled = vec![];
+    for (index, page_id) in page_ids(doc) {
+        let visual = VisualPage::new(doc, page_id)?;
+        let fit = (width / visual.width).min(height / visual.height);
+        let scale = match setting.policy {
+            _ if (fit - 1.0).abs() < SCALE_TOLERANCE => 1.0,
+            FitPolicy::Fit => fit,
+            FitPolicy::Center => fit.min(1.0),
+        };
+        if scale != 1.0 {
+            scaled.push((index, scale));
+        }
+        let dx = (width - visual.width * scale) / 2.0;
+        let dy = (height - visual.height * scale) / 2.0;
+        let [a, b, c, d, e, f] = visual.inverse();
+        let matrix = [
+            a * scale,
+            b * scale,
+            c * scale,
+            d * scale,
+            e * scale + dx,
+            f * scale + dy,
+        ];
+        let page_box = Object::Array(vec![0.into(), 0.into(), width.into(), height.into()]);
+        let page = doc.get_dictionary_mut(page_id)?;
+        page.set("MediaBox", page_box.clone());
+        page.set("CropBox", page_box);
+        page.remove(b"Rotate");
+        for key in PAGE_BOXES {
+            page.remove(key);
+        }
+        if !is_identity(&matrix) {
+            transform_content(doc, page_id, &matrix)?;
+            transform_annotations(doc, page_id, &matrix)?;
+        }
+    }
+    Ok(scaled)
+}
+
+/// width and height of paper in points
+pub fn paper(setting: &PageSetting) -> (f32, f32) {
+    let (width, height) = setting.size.dimensions();
+    match setting.orientation {
+        PageOrientation::Landscape => (height, width),
+        PageOrientation::Portrait => (width, height),
+    }
+}
+
+fn is_identity(matrix: &[f32; 6]) -> bool {
+    matrix
+        .iter()
+        .zip([1.0, 0.0, 0.0, 1.0, 0.0, 0.0])
+        .all(|(value, identity)| (value - identity).abs() < 1e-3)
+}
+
+/// wrap content of page in a graphics state with the transformation
+fn transform_content(
+    doc: &mut Document,
+    page_id: ObjectId,
+    matrix: &[f32; 6],
+) -> anyhow::Result<()> {
+    let mut contents = match doc.get_dictionary(page_id)?.get(b"Contents") {
+        Ok(Object::Reference(id)) => vec![Object::Reference(*id)],
+        Ok(Object::Array(contents)) => contents.clone(),
+        _ => vec![],
+    };
+    let transform = Operation::new("cm", matrix.iter().map(|n| (*n).into()).collect());
+    let mut content = b"q\n".to_vec();
+    content.extend(
+        Content {
+            operations: vec![transform],
+        }
+        .encode()?,
+    );
+    let save = doc.add_object(Stream::new(Dictionary::new(), content));
+    let restore = doc.add_object(Stream::new(Dictionary::new(), b"\nQ\n".to_vec()));
+    contents.insert(0, Object::Reference(save));
+    contents.push(Object::Reference(restore));
+    doc.get_dictionary_mut(page_id)?.set("Contents", contents);
+    Ok(())
+}
+
+/// move Rect and QuadPoints of annotations along with content, such as toc links
+fn transform_annotations(
+    doc: &mut Document,
+    page_id: ObjectId,
+    matrix: &[f32; 6],
+) -> anyhow::Result<()> {
+    let annotations = match doc.get_dictionary(page_id)?.get(b"Annots") {
+        Ok(Object::Reference(id)) => doc.get_object(*id)?.as_array()?.clone(),
+        Ok(Object::Array(annotations)) => annotations.clone(),
+        _ => return Ok(()),
+    };
+    for annotation in annotations {
+        let annotation = match annotation.as_reference() {
+            Ok(id) => match doc.get_dictionary_mut(id) {
+                Ok(annotation) => annotation,
+                Err(_) => continue,
+            },
+            // direct annotations are rare and left as they are
+            Err(_) => continue,
+        };
+        if let Some(points) = numbers(annotation.get(b"Rect").ok()) {
+            if points.len() == 4 {
+                let (x0, y0) = apply(matrix, points[0], points[1]);
+                let (x1, y1) = apply(matrix, points[2], points[3]);
+                annotation.set(
+                    "Rect",
+                    vec![
+                        x0.min(x1).into(),
+                        y0.min(y1).into(),
+                        x0.max(x1).into(),
+                        y0.max(y1).into(),
+                    ],
+                );
+            }
+        }
+        if let Some(points) = numbers(annotation.get(b"QuadPoints").ok()) {
+            let points = points
+                .chunks(2)
+                .filter(|p| p.len() == 2)
+                .flat_map(|p| {
+                    let (x, y) = apply(matrix, p[0], p[1]);
+                    [x.into(), y.into()]
+                })
+                .collect::<Vec<Object>>();
+            annotation.set("QuadPoints", points);
+        }
+    }
+    Ok(())
+}
+
+fn numbers(object: Option<&Object>) -> Option<Vec<f32>> {
+    object?
+        .as_array()
+        .ok()?
+        .iter()
+        .map(|n| n.as_float().ok())
+        .collect()
+}
+
+fn apply(matrix: &[f32; 6], x: f32, y: f32) -> (f32, f32) {
+    let [a, b, c, d, e, f] = matrix;
+    (a * x + c * y + e, b * x + d * y + f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::pdf::PaperSize;
+    use lopdf::dictionary;
+
+    #[test]
+    fn normalize_test() -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let link = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Link",
+            "Rect" => vec![0.into(), 0.into(), 100.into(), 20.into()],
+        });
+        let mut kids = vec![];
+        for (media_box, rotate) in [
+            // letter landscape by rotation, A4 landscape, A4 landscape with small difference
+            (vec![0.into(), 0.into(), 612.into(), 792.into()], 90),
+            (vec![0.into(), 0.into(), 841.89.into(), 595.28.into()], 0),
+            (vec![0.into(), 0.into(), 842.into(), 595.into()], 0),
+        ] {
+            kids.push(
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "MediaBox" => media_box,
+                    "Rotate" => rotate,
+                    "Annots" => vec![link.into()],
+                })
+                .into(),
+            );
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => kids, "Count" => 3 }),
+        );
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog);
+
+        let setting = PageSetting {
+            size: PaperSize::A4,
+            ..Default::default()
+        };
+        let scaled = normalize(&mut doc, &setting)?;
+        assert_eq!(scaled.len(), 1);
+        assert_eq!(scaled[0].0, 0);
+        for (_, page_id) in page_ids(&doc) {
+            let visual = VisualPage::new(&doc, page_id)?;
+            assert_eq!((visual.width, visual.height), (841.89, 595.28));
+        }
+        Ok(())
+    }
+}
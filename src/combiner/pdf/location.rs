@@ -2,12 +2,34 @@ use std::path::{Path, PathBuf};
 
 use serde::Serialize;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct Location {
     pub id: Option<usize>,
     pub title: String,
     pub page: usize,
     pub path: PathBuf,
+    /// divider page, heading of the following outputs
+    pub divider: bool,
+    /// source rtf is embedded in combined pdf
+    pub attachment: bool,
+    /// heading of the group starting from this output
+    pub group: Option<String>,
+    /// level in multi-level toc, starting from 0
+    pub level: usize,
+    /// group or section heading of multi-level toc, linked to its first output
+    pub heading: bool,
+    /// last page of heading, shown as page range in toc
+    pub last_page: Option<usize>,
+}
+
+impl Location {
+    /// page starting from 1 as shown in toc, or page range of heading, such as "12-30"
+    pub fn page_number(&self) -> String {
+        match self.last_page {
+            Some(last) => format!("{}-{}", self.page + 1, last + 1),
+            None => (self.page + 1).to_string(),
+        }
+    }
 }
 
 pub struct LocationManager {
@@ -35,10 +57,43 @@ impl LocationManager {
             title: title.into(),
             page,
             path: path.into(),
+            divider: false,
+            attachment: false,
+            group: None,
+            level: 0,
+            heading: false,
+            last_page: None,
         });
         self.total_pages += total_pages;
         self
     }
+    pub fn push_divider(
+        &mut self,
+        id: Option<usize>,
+        title: &str,
+        total_pages: usize,
+        path: &Path,
+    ) -> &mut Self {
+        self.push(id, title, total_pages, path);
+        if let Some(location) = self.data.last_mut() {
+            location.divider = true;
+        }
+        self
+    }
+    /// mark the last location as having its source attached
+    pub fn mark_attachment(&mut self) -> &mut Self {
+        if let Some(location) = self.data.last_mut() {
+            location.attachment = true;
+        }
+        self
+    }
+    /// start a group of outputs from the last location
+    pub fn set_group(&mut self, title: &str) -> &mut Self {
+        if let Some(location) = self.data.last_mut() {
+            location.group = Some(title.into());
+        }
+        self
+    }
     pub fn insert_head(
         &mut self,
         id: Option<usize>,
@@ -56,6 +111,12 @@ impl LocationManager {
                 title: title.into(),
                 page: 0,
                 path: path.into(),
+                divider: false,
+                attachment: false,
+                group: None,
+                level: 0,
+                heading: false,
+                last_page: None,
             },
         );
         self.total_pages += total_pages;
@@ -64,6 +125,21 @@ impl LocationManager {
     pub fn data(&self) -> Vec<Location> {
         self.data.clone()
     }
+    pub fn total_pages(&self) -> usize {
+        self.total_pages
+    }
+    /// pages of every location
+    pub fn pages(&self) -> Vec<usize> {
+        let mut pages = self
+            .data
+            .windows(2)
+            .map(|pair| pair[1].page - pair[0].page)
+            .collect::<Vec<usize>>();
+        if let Some(last) = self.data.last() {
+            pages.push(self.total_pages - last.page);
+        }
+        pages
+    }
 }
 
 #[cfg(test)]
@@ -78,7 +154,7 @@ mod tests {
         manager.insert_head(None, "toc", 1, Path::new(""));
         assert_eq!(manager.data().len(), 4);
         assert_eq!(manager.data().first().unwrap().title, "toc");
-        assert_eq!(manager.data().last().unwrap().page, 5);
+        assert_eq!(manager.data().last().unwrap().page, 6);
         assert_eq!(manager.total_pages, 7);
     }
 }
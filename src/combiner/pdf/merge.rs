@@ -0,0 +1,115 @@
+use super::location::Location;
+use anyhow::anyhow;
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, StringFormat};
+use std::collections::BTreeMap;
+
+/// page attributes which could be inherited from parent page tree nodes
+const INHERITABLE_ATTRIBUTES: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// merge all pdf in locations into one document, pages are appended in the order of locations
+pub fn merge(locations: &[Location]) -> anyhow::Result<Document> {
+    let mut document = Document::with_version("1.5");
+    let mut max_id = 1;
+    let mut pages: Vec<ObjectId> = vec![];
+
+    for location in locations {
+        let mut doc = Document::load(&location.path)?;
+        doc.renumber_objects_with(max_id);
+        max_id = doc.max_id + 1;
+
+        let doc_pages = doc.get_pages().into_values().collect::<Vec<ObjectId>>();
+        for page_id in doc_pages.iter() {
+            let page = inherit_page_attributes(&doc, *page_id)?;
+            document.objects.insert(*page_id, Object::Dictionary(page));
+        }
+        for (object_id, object) in doc.objects.into_iter() {
+            // catalog, page tree and outline of source documents will be rebuilt
+            match object.type_name().unwrap_or(b"") {
+                b"Catalog" | b"Pages" | b"Page" | b"Outlines" => {}
+                _ => {
+                    document.objects.entry(object_id).or_insert(object);
+                }
+            }
+        }
+        pages.extend(doc_pages);
+    }
+
+    document.max_id = max_id;
+    let pages_id = document.new_object_id();
+    for page_id in pages.iter() {
+        document
+            .get_dictionary_mut(*page_id)?
+            .set("Parent", Object::Reference(pages_id));
+    }
+    document.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Count" => pages.len() as i64,
+            "Kids" => pages.iter().map(|id| Object::Reference(*id)).collect::<Vec<Object>>(),
+        }),
+    );
+    let catalog_id = document.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    document.trailer.set("Root", catalog_id);
+    Ok(document)
+}
+
+/// copy inheritable attributes from parent page tree nodes into the page itself,
+/// since the page tree of source document will be dropped after merging
+fn inherit_page_attributes(doc: &Document, page_id: ObjectId) -> anyhow::Result<Dictionary> {
+    let mut page = doc.get_dictionary(page_id)?.clone();
+    let mut parent = page.get(b"Parent").and_then(Object::as_reference).ok();
+    while let Some(parent_id) = parent {
+        let node = doc.get_dictionary(parent_id)?;
+        for key in INHERITABLE_ATTRIBUTES {
+            if !page.has(key) {
+                if let Ok(value) = node.get(key) {
+                    page.set(key, value.clone());
+                }
+            }
+        }
+        parent = node.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+    if !page.has(b"MediaBox") {
+        return Err(anyhow!("page {:?} missing MediaBox", page_id));
+    }
+    Ok(page)
+}
+
+/// encode text string in pdf, non-ascii text such as chinese title must be written as UTF-16BE with BOM
+pub fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::string_literal(text);
+    }
+    let mut bytes = vec![0xFE, 0xFF];
+    text.encode_utf16()
+        .for_each(|c| bytes.extend(c.to_be_bytes()));
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+/// page object ids of a document, ordered by page number starting from 0
+pub fn page_ids(doc: &Document) -> BTreeMap<usize, ObjectId> {
+    doc.get_pages()
+        .into_iter()
+        .map(|(number, id)| (number as usize - 1, id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn text_string_test() {
+        assert_eq!(text_string("Cover"), Object::string_literal("Cover"));
+        assert_eq!(
+            text_string("目录"),
+            Object::String(
+                vec![0xFE, 0xFF, 0x76, 0xEE, 0x5F, 0x55],
+                StringFormat::Hexadecimal
+            )
+        );
+    }
+}
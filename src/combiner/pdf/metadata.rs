@@ -0,0 +1,334 @@
+use super::merge::text_string;
+use lopdf::{dictionary, Document, Object, Stream};
+use std::{
+    collections::BTreeMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// namespace of custom properties in XMP
+const CUSTOM_NAMESPACE: &str = "http://ns.fusion.app/custom/1.0/";
+const CUSTOM_PREFIX: &str = "fusion";
+
+/// document information written into both Info dictionary and XMP metadata
+#[derive(Debug, Clone, Default)]
+pub struct Metadata {
+    pub title: String,
+    pub author: String,
+    pub subject: String,
+    pub keywords: Vec<String>,
+    pub creator: String,
+    pub producer: String,
+    /// RFC 3066 language tag, such as "en-US"
+    pub language: String,
+    pub created: Timestamp,
+    /// custom properties, such as study ID and data cut-off date
+    pub properties: BTreeMap<String, String>,
+    /// PDF/A identification, part and conformance level, such as (1, "B")
+    pub pdfa: Option<(u8, String)>,
+}
+
+/// UTC date time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timestamp {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: u64,
+    minute: u64,
+    second: u64,
+}
+
+impl Timestamp {
+    pub fn now() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Timestamp::from_unix(seconds)
+    }
+
+    /// convert seconds since unix epoch to calendar date, see http://howardhinnant.github.io/date_algorithms.html
+    pub fn from_unix(seconds: u64) -> Self {
+        let days = (seconds / 86400) as i64 + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        let rest = seconds % 86400;
+        Timestamp {
+            year,
+            month,
+            day,
+            hour: rest / 3600,
+            minute: rest % 3600 / 60,
+            second: rest % 60,
+        }
+    }
+
+    /// calendar date, such as "2024-10-01"
+    pub fn date(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+
+    /// date string in Info dictionary, such as "D:20241001083000Z"
+    pub fn pdf(&self) -> String {
+        format!(
+            "D:{:04}{:02}{:02}{:02}{:02}{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+
+    /// date string in XMP, such as "2024-10-01T08:30:00Z"
+    pub fn xmp(&self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// write Info dictionary and XMP metadata stream, values in both of them are kept identical
+pub fn write_metadata(doc: &mut Document, metadata: &Metadata) -> anyhow::Result<()> {
+    let created = metadata.created.pdf();
+    let mut info = dictionary! {
+        "Title" => text_string(&metadata.title),
+        "Creator" => text_string(&metadata.creator),
+        "Producer" => text_string(&metadata.producer),
+        "CreationDate" => Object::string_literal(created.as_str()),
+        "ModDate" => Object::string_literal(created.as_str()),
+    };
+    if !metadata.author.is_empty() {
+        info.set("Author", text_string(&metadata.author));
+    }
+    if !metadata.subject.is_empty() {
+        info.set("Subject", text_string(&metadata.subject));
+    }
+    if !metadata.keywords.is_empty() {
+        info.set("Keywords", text_string(&metadata.keywords.join(", ")));
+    }
+    for (key, value) in &metadata.properties {
+        info.set(property_name(key).as_bytes(), text_string(value));
+    }
+    let info = doc.add_object(info);
+    doc.trailer.set("Info", info);
+
+    // metadata stream must be left uncompressed to be readable by non-pdf tools
+    let xmp = Stream::new(
+        dictionary! {
+            "Type" => "Metadata",
+            "Subtype" => "XML",
+        },
+        xmp_packet(metadata).into_bytes(),
+    )
+    .with_compression(false);
+    let xmp = doc.add_object(xmp);
+    let catalog = doc.catalog_mut()?;
+    catalog.set("Metadata", xmp);
+    if !metadata.language.is_empty() {
+        catalog.set("Lang", text_string(&metadata.language));
+    }
+    Ok(())
+}
+
+/// raise pdf version of document to `minimum` when it is lower
+pub fn require_version(doc: &mut Document, minimum: &str) {
+    if version_number(&doc.version) < version_number(minimum) {
+        doc.version = minimum.into();
+    }
+}
+
+/// major and minor numbers of pdf version such as "1.7", so that "1.10" is above "1.5"
+fn version_number(version: &str) -> (u32, u32) {
+    let mut numbers = version.trim().split('.').map(|number| {
+        number
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect::<String>()
+            .parse()
+            .unwrap_or(0)
+    });
+    (numbers.next().unwrap_or(0), numbers.next().unwrap_or(0))
+}
+
+fn xmp_packet(metadata: &Metadata) -> String {
+    let created = metadata.created.xmp();
+    let mut properties = vec![
+        "<dc:format>application/pdf</dc:format>".to_string(),
+        format!(
+            "<dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:title>",
+            xml_escape(&metadata.title)
+        ),
+    ];
+    if !metadata.author.is_empty() {
+        properties.push(format!(
+            "<dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>",
+            xml_escape(&metadata.author)
+        ));
+    }
+    if !metadata.subject.is_empty() {
+        properties.push(format!(
+            "<dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:description>",
+            xml_escape(&metadata.subject)
+        ));
+    }
+    if !metadata.keywords.is_empty() {
+        properties.push(format!(
+            "<dc:subject><rdf:Bag>{}</rdf:Bag></dc:subject>",
+            metadata
+                .keywords
+                .iter()
+                .map(|k| format!("<rdf:li>{}</rdf:li>", xml_escape(k)))
+                .collect::<String>()
+        ));
+        properties.push(format!(
+            "<pdf:Keywords>{}</pdf:Keywords>",
+            xml_escape(&metadata.keywords.join(", "))
+        ));
+    }
+    if !metadata.language.is_empty() {
+        properties.push(format!(
+            "<dc:language><rdf:Bag><rdf:li>{}</rdf:li></rdf:Bag></dc:language>",
+            xml_escape(&metadata.language)
+        ));
+    }
+    properties.push(format!(
+        "<xmp:CreatorTool>{}</xmp:CreatorTool>",
+        xml_escape(&metadata.creator)
+    ));
+    properties.push(format!("<xmp:CreateDate>{}</xmp:CreateDate>", created));
+    properties.push(format!("<xmp:ModifyDate>{}</xmp:ModifyDate>", created));
+    properties.push(format!("<xmp:MetadataDate>{}</xmp:MetadataDate>", created));
+    properties.push(format!(
+        "<pdf:Producer>{}</pdf:Producer>",
+        xml_escape(&metadata.producer)
+    ));
+    for (key, value) in &metadata.properties {
+        let name = property_name(key);
+        properties.push(format!(
+            "<{prefix}:{name}>{value}</{prefix}:{name}>",
+            prefix = CUSTOM_PREFIX,
+            name = name,
+            value = xml_escape(value)
+        ));
+    }
+    if let Some((part, conformance)) = &metadata.pdfa {
+        properties.push(format!(
+            "<pdfaid:part>{}</pdfaid:part><pdfaid:conformance>{}</pdfaid:conformance>",
+            part, conformance
+        ));
+        // custom properties must be described by an extension schema in PDF/A
+        if !metadata.properties.is_empty() {
+            properties.push(extension_schema(metadata.properties.keys()));
+        }
+    }
+    format!(
+        r#"<?xpacket begin="{bom}" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+<rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmlns:pdf="http://ns.adobe.com/pdf/1.3/" xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/" xmlns:pdfaExtension="http://www.aiim.org/pdfa/ns/extension/" xmlns:pdfaSchema="http://www.aiim.org/pdfa/ns/schema#" xmlns:pdfaProperty="http://www.aiim.org/pdfa/ns/property#" xmlns:{prefix}="{namespace}">
+{properties}
+</rdf:Description>
+</rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        bom = '\u{feff}',
+        prefix = CUSTOM_PREFIX,
+        namespace = CUSTOM_NAMESPACE,
+        properties = properties.join("\n"),
+    )
+}
+
+fn extension_schema<'a>(keys: impl Iterator<Item = &'a String>) -> String {
+    let properties = keys
+        .map(|key| {
+            format!(
+                "<rdf:li rdf:parseType=\"Resource\"><pdfaProperty:name>{}</pdfaProperty:name><pdfaProperty:valueType>Text</pdfaProperty:valueType><pdfaProperty:category>external</pdfaProperty:category><pdfaProperty:description>{}</pdfaProperty:description></rdf:li>",
+                property_name(key),
+                xml_escape(key)
+            )
+        })
+        .collect::<String>();
+    format!(
+        "<pdfaExtension:schemas><rdf:Bag><rdf:li rdf:parseType=\"Resource\"><pdfaSchema:schema>Custom properties</pdfaSchema:schema><pdfaSchema:namespaceURI>{}</pdfaSchema:namespaceURI><pdfaSchema:prefix>{}</pdfaSchema:prefix><pdfaSchema:property><rdf:Seq>{}</rdf:Seq></pdfaSchema:property></rdf:li></rdf:Bag></pdfaExtension:schemas>",
+        CUSTOM_NAMESPACE, CUSTOM_PREFIX, properties
+    )
+}
+
+/// key of custom property as a valid XML name, such as "Study ID" => "StudyID"
+fn property_name(key: &str) -> String {
+    let name = key
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect::<String>();
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => name,
+        _ => format!("_{}", name),
+    }
+}
+
+fn xml_escape(source: &str) -> String {
+    source
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn timestamp_test() {
+        assert_eq!(Timestamp::from_unix(0).xmp(), "1970-01-01T00:00:00Z");
+        let timestamp = Timestamp::from_unix(1_700_000_000);
+        assert_eq!(timestamp.xmp(), "2023-11-14T22:13:20Z");
+        assert_eq!(timestamp.pdf(), "D:20231114221320Z");
+        assert_eq!(timestamp.date(), "2023-11-14");
+        assert_eq!(
+            Timestamp::from_unix(951_782_400).xmp(),
+            "2000-02-29T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn xmp_packet_test() {
+        let mut metadata = Metadata {
+            title: "T&F <final>".into(),
+            keywords: vec!["CSR".into(), "TFL".into()],
+            properties: BTreeMap::from([("Study ID".into(), "AK112-303".into())]),
+            ..Default::default()
+        };
+        let packet = xmp_packet(&metadata);
+        assert!(packet.contains("T&amp;F &lt;final&gt;"));
+        assert!(packet.contains("<pdf:Keywords>CSR, TFL</pdf:Keywords>"));
+        assert!(packet.contains("<fusion:StudyID>AK112-303</fusion:StudyID>"));
+        assert!(!packet.contains("pdfaExtension:schemas"));
+        metadata.pdfa = Some((1, "B".into()));
+        let packet = xmp_packet(&metadata);
+        assert!(packet.contains("<pdfaid:part>1</pdfaid:part>"));
+        assert!(packet.contains("<pdfaProperty:name>StudyID</pdfaProperty:name>"));
+    }
+
+    #[test]
+    fn require_version_test() {
+        let mut doc = Document::with_version("1.10");
+        require_version(&mut doc, "1.5");
+        assert_eq!(doc.version, "1.10");
+        require_version(&mut doc, "2.0");
+        assert_eq!(doc.version, "2.0");
+        assert!(version_number("1.7") < version_number("1.10"));
+    }
+
+    #[test]
+    fn property_name_test() {
+        assert_eq!(property_name("Study ID"), "StudyID");
+        assert_eq!(property_name("Data-Cutoff"), "DataCutoff");
+        assert_eq!(property_name("2nd Lock"), "_2ndLock");
+    }
+}
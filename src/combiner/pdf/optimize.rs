@@ -0,0 +1,260 @@
+use super::metadata::require_version;
+use lopdf::{Dictionary, Document, Object, ObjectId, SaveOptions};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// max objects packed into one object stream
+const OBJECT_STREAM_SIZE: usize = 100;
+/// font programs and images are deduplicated first, then fonts referring to them become identical
+const DEDUPLICATE_PASSES: usize = 4;
+
+/// replace identical font and image objects by a single copy, return the number of removed objects
+pub fn deduplicate(doc: &mut Document) -> anyhow::Result<usize> {
+    let mut removed = 0;
+    for _ in 0..DEDUPLICATE_PASSES {
+        let duplicates = duplicates(doc);
+        if duplicates.is_empty() {
+            break;
+        }
+        for id in duplicates.keys() {
+            doc.objects.remove(id);
+        }
+        doc.objects
+            .values_mut()
+            .for_each(|object| replace_references(object, &duplicates));
+        replace_references_in(&mut doc.trailer, &duplicates);
+        removed += duplicates.len();
+    }
+    Ok(removed)
+}
+
+/// map duplicated objects to the first object with the same content
+fn duplicates(doc: &Document) -> HashMap<ObjectId, ObjectId> {
+    let font_programs = doc
+        .objects
+        .values()
+        .filter_map(|object| match object {
+            Object::Dictionary(dict) if dict.has_type(b"FontDescriptor") => Some(dict),
+            _ => None,
+        })
+        .flat_map(|dict| {
+            [b"FontFile".as_slice(), b"FontFile2", b"FontFile3"]
+                .into_iter()
+                .filter_map(|key| dict.get(key).and_then(Object::as_reference).ok())
+        })
+        .collect::<Vec<ObjectId>>();
+    let mut canonical: HashMap<Vec<u8>, ObjectId> = HashMap::new();
+    let mut result = HashMap::new();
+    for (id, object) in &doc.objects {
+        let candidate = match object {
+            Object::Stream(stream) => {
+                font_programs.contains(id)
+                    || stream.dict.get(b"Subtype").and_then(Object::as_name).ok()
+                        == Some(b"Image".as_slice())
+            }
+            Object::Dictionary(dict) => dict.has_type(b"Font") || dict.has_type(b"FontDescriptor"),
+            _ => false,
+        };
+        if !candidate {
+            continue;
+        }
+        let mut hasher = Sha256::new();
+        digest(object, &mut hasher);
+        let hash = hasher.finalize().to_vec();
+        match canonical.get(&hash) {
+            Some(first) => {
+                result.insert(*id, *first);
+            }
+            None => {
+                canonical.insert(hash, *id);
+            }
+        }
+    }
+    result
+}
+
+/// point references to duplicated objects to the canonical ones, unreachable objects included
+fn replace_references(object: &mut Object, duplicates: &HashMap<ObjectId, ObjectId>) {
+    match object {
+        Object::Reference(id) => {
+            if let Some(canonical) = duplicates.get(id) {
+                *id = *canonical;
+            }
+        }
+        Object::Array(array) => array
+            .iter_mut()
+            .for_each(|item| replace_references(item, duplicates)),
+        Object::Dictionary(dict) => replace_references_in(dict, duplicates),
+        Object::Stream(stream) => replace_references_in(&mut stream.dict, duplicates),
+        _ => {}
+    }
+}
+
+fn replace_references_in(dict: &mut Dictionary, duplicates: &HashMap<ObjectId, ObjectId>) {
+    dict.iter_mut()
+        .for_each(|(_, value)| replace_references(value, duplicates));
+}
+
+/// compress streams without any filter, streams such as XMP metadata which disallow compression are kept
+pub fn compress(doc: &mut Document) {
+    doc.compress();
+}
+
+/// save document with objects other than streams packed into compressed object streams,
+/// cross reference stream is required by object streams, so pdf version is raised to 1.5 at least
+pub fn save_packed(doc: &mut Document, path: &Path) -> anyhow::Result<()> {
+    require_version(doc, "1.5");
+    let options = SaveOptions::builder()
+        .use_object_streams(true)
+        .use_xref_streams(true)
+        .max_objects_per_stream(OBJECT_STREAM_SIZE)
+        .build();
+    let mut file = BufWriter::new(File::create(path)?);
+    doc.save_with_options(&mut file, options)?;
+    file.flush()?;
+    Ok(())
+}
+
+/// size of document if saved without object streams
+pub fn saved_size(doc: &mut Document) -> anyhow::Result<u64> {
+    let mut counter = Counter(0);
+    doc.save_to(&mut counter)?;
+    Ok(counter.0)
+}
+
+/// writer counting bytes only
+struct Counter(u64);
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// feed object into hasher, dictionaries are hashed in key order
+fn digest(object: &Object, hasher: &mut Sha256) {
+    match object {
+        Object::Null => hasher.update(b"n"),
+        Object::Boolean(value) => hasher.update(if *value { b"t" } else { b"f" }),
+        Object::Integer(value) => {
+            hasher.update(b"i");
+            hasher.update(value.to_be_bytes());
+        }
+        Object::Real(value) => {
+            hasher.update(b"r");
+            hasher.update(value.to_be_bytes());
+        }
+        Object::Name(name) => {
+            hasher.update(b"/");
+            hasher.update((name.len() as u64).to_be_bytes());
+            hasher.update(name);
+        }
+        Object::String(text, _) => {
+            hasher.update(b"s");
+            hasher.update((text.len() as u64).to_be_bytes());
+            hasher.update(text);
+        }
+        Object::Array(array) => {
+            hasher.update(b"[");
+            hasher.update((array.len() as u64).to_be_bytes());
+            array.iter().for_each(|item| digest(item, hasher));
+        }
+        Object::Dictionary(dict) => digest_dictionary(dict, hasher),
+        Object::Stream(stream) => {
+            digest_dictionary(&stream.dict, hasher);
+            hasher.update((stream.content.len() as u64).to_be_bytes());
+            hasher.update(&stream.content);
+        }
+        Object::Reference((id, generation)) => {
+            hasher.update(b"R");
+            hasher.update(id.to_be_bytes());
+            hasher.update(generation.to_be_bytes());
+        }
+    }
+}
+
+fn digest_dictionary(dict: &Dictionary, hasher: &mut Sha256) {
+    let mut entries = dict.iter().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    hasher.update(b"<<");
+    hasher.update((entries.len() as u64).to_be_bytes());
+    for (key, value) in entries {
+        hasher.update((key.len() as u64).to_be_bytes());
+        hasher.update(key);
+        digest(value, hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+    use std::fs;
+    #[test]
+    fn deduplicate_test() -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.5");
+        let font_file = || Stream::new(dictionary! {}, b"font program".to_vec());
+        let mut fonts = vec![];
+        for _ in 0..2 {
+            let program = doc.add_object(font_file());
+            let descriptor = doc.add_object(dictionary! {
+                "Type" => "FontDescriptor",
+                "FontName" => "SimSun",
+                "FontFile2" => program,
+            });
+            fonts.push(doc.add_object(dictionary! {
+                "Type" => "Font",
+                "Subtype" => "TrueType",
+                "BaseFont" => "SimSun",
+                "FontDescriptor" => descriptor,
+            }));
+        }
+        doc.add_object(dictionary! {
+            "Font" => dictionary! { "F1" => fonts[0], "F2" => fonts[1] },
+        });
+        assert_eq!(deduplicate(&mut doc)?, 3);
+        assert_eq!(doc.objects.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn save_packed_test() -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.4");
+        let pages_id = doc.new_object_id();
+        let content = doc.add_object(Stream::new(dictionary! {}, b"0 0 m".to_vec()));
+        let page = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            "Contents" => content,
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog);
+        let path = std::env::temp_dir().join("fusion_save_packed_test.pdf");
+        save_packed(&mut doc, &path)?;
+        assert!(fs::read(&path)?.windows(7).any(|w| w == b"/ObjStm"));
+        let loaded = Document::load(&path)?;
+        assert_eq!(loaded.version, "1.5");
+        assert_eq!(loaded.get_pages().len(), 1);
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,357 @@
+use super::{
+    location::Location,
+    merge::{page_ids, text_string},
+};
+use crate::{
+    config::{
+        locale::Catalog,
+        pdf::{OutlineSetting, TocLevelSetting},
+    },
+    top::title_number,
+};
+use anyhow::anyhow;
+use lopdf::{dictionary, Document, Object, ObjectId};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlineNode {
+    /// key to merge siblings, group symbol or section number, empty for outputs
+    key: String,
+    pub title: String,
+    /// page index in combined pdf, starting from 0
+    pub page: usize,
+    /// id of the location, or its first output for group and section nodes
+    pub id: Option<usize>,
+    pub children: Vec<OutlineNode>,
+}
+
+impl OutlineNode {
+    fn new(key: &str, title: &str, page: usize, id: Option<usize>) -> Self {
+        OutlineNode {
+            key: key.into(),
+            title: title.into(),
+            page,
+            id,
+            children: vec![],
+        }
+    }
+}
+
+/// build outline tree from locations, cover and toc (location without id), dividers and groups of outputs
+/// always stay on top level
+///
+/// outputs after a divider or group nest under it, then under group and section nodes according to setting,
+/// a node is placed into the last sibling with the same key only, so the order of outline follows the order of pages
+pub fn outline_tree(
+    locations: &[Location],
+    setting: &OutlineSetting,
+    catalog: &Catalog,
+) -> Vec<OutlineNode> {
+    let mut nodes: Vec<OutlineNode> = vec![];
+    let mut heading: Option<usize> = None;
+    for location in locations.iter().filter(|l| !l.title.is_empty()) {
+        let leaf = OutlineNode::new("", &location.title, location.page, location.id);
+        if location.id.is_none() || location.divider {
+            nodes.push(leaf);
+            heading = location.divider.then_some(nodes.len() - 1);
+            continue;
+        }
+        if let Some(group) = &location.group {
+            nodes.push(OutlineNode::new("", group, location.page, location.id));
+            heading = Some(nodes.len() - 1);
+        }
+        let mut siblings = match heading {
+            Some(index) => &mut nodes[index].children,
+            None => &mut nodes,
+        };
+        if !setting.nested {
+            siblings.push(leaf);
+            continue;
+        }
+        for (key, title) in ancestors(&location.title, setting, catalog) {
+            let index = match siblings.last() {
+                Some(last) if !key.is_empty() && last.key.eq(&key) => siblings.len() - 1,
+                _ => {
+                    siblings.push(OutlineNode::new(&key, &title, location.page, location.id));
+                    siblings.len() - 1
+                }
+            };
+            siblings = &mut siblings[index].children;
+        }
+        siblings.push(leaf);
+    }
+    nodes
+}
+
+/// group and section nodes above an output, as pairs of key and title
+fn ancestors(title: &str, setting: &OutlineSetting, catalog: &Catalog) -> Vec<(String, String)> {
+    let mut result = vec![];
+    if let Some((symbol, number)) = title_number(title, Some(catalog)) {
+        if setting.group_by_type {
+            if let Some(group) = catalog.group(&symbol) {
+                result.push((symbol, group.into()));
+            }
+        }
+        let levels = number.split('.').collect::<Vec<&str>>();
+        let depth = setting.depth.min(levels.len() - 1);
+        for level in 1..=depth {
+            let section = levels[0..level].join(".");
+            result.push((section.clone(), setting.section_title(&section)));
+        }
+    }
+    result
+}
+
+/// entries of multi-level toc from outline tree in the order of pages, so that toc mirrors the outline,
+/// headings link to their first output and span pages until the next entry of the same or upper level
+///
+/// `total_pages` is pages of all locations, ending the range of the last heading
+pub fn toc_items(
+    locations: &[Location],
+    tree: &[OutlineNode],
+    setting: &TocLevelSetting,
+    total_pages: usize,
+) -> Vec<Location> {
+    let locations = locations
+        .iter()
+        .filter_map(|l| l.id.map(|id| (id, l)))
+        .collect::<HashMap<usize, &Location>>();
+    let mut items = vec![];
+    flatten(tree, 0, &locations, &mut items);
+    if setting.page_range {
+        for index in 0..items.len() {
+            let level = items[index].level;
+            // only headings with entries below have a range
+            match items.get(index + 1) {
+                Some(next) if next.level > level => {}
+                _ => continue,
+            }
+            let end = items[index + 1..]
+                .iter()
+                .find(|next| next.level <= level)
+                .map_or(total_pages, |next| next.page);
+            items[index].last_page = Some(end.saturating_sub(1).max(items[index].page));
+        }
+    }
+    items
+}
+
+/// nodes of outputs and dividers keep their locations, other nodes become headings
+fn flatten(
+    nodes: &[OutlineNode],
+    level: usize,
+    locations: &HashMap<usize, &Location>,
+    items: &mut Vec<Location>,
+) {
+    for node in nodes {
+        let location = node.id.and_then(|id| locations.get(&id));
+        items.push(match location {
+            Some(location) if node.children.is_empty() || location.divider => Location {
+                level,
+                ..(*location).clone()
+            },
+            _ => Location {
+                id: node.id,
+                title: node.title.clone(),
+                page: node.page,
+                level,
+                heading: true,
+                ..Default::default()
+            },
+        });
+        flatten(&node.children, level + 1, locations, items);
+    }
+}
+
+/// write outline tree into document and attach it to catalog
+pub fn write_outline(doc: &mut Document, nodes: &[OutlineNode]) -> anyhow::Result<()> {
+    if nodes.is_empty() {
+        return Ok(());
+    }
+    let pages = page_ids(doc);
+    let outline_id = doc.new_object_id();
+    let (first, last) = write_children(doc, &pages, outline_id, nodes)?;
+    doc.objects.insert(
+        outline_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => first,
+            "Last" => last,
+            "Count" => nodes.len() as i64,
+        }),
+    );
+    let catalog = doc.catalog_mut()?;
+    catalog.set("Outlines", Object::Reference(outline_id));
+    catalog.set("PageMode", "UseOutlines");
+    Ok(())
+}
+
+/// write siblings under parent, return the first and last item, nodes with children are closed
+fn write_children(
+    doc: &mut Document,
+    pages: &BTreeMap<usize, ObjectId>,
+    parent: ObjectId,
+    nodes: &[OutlineNode],
+) -> anyhow::Result<(ObjectId, ObjectId)> {
+    let ids = nodes
+        .iter()
+        .map(|_| doc.new_object_id())
+        .collect::<Vec<ObjectId>>();
+    for (index, node) in nodes.iter().enumerate() {
+        let page = pages
+            .get(&node.page)
+            .ok_or(anyhow!("page {} not found in combined pdf", node.page))?;
+        let mut item = dictionary! {
+            "Title" => text_string(&node.title),
+            "Parent" => parent,
+            "Dest" => vec![
+                Object::Reference(*page),
+                Object::Name(b"XYZ".into()),
+                Object::Null,
+                Object::Null,
+                Object::Null,
+            ],
+        };
+        if index > 0 {
+            item.set("Prev", ids[index - 1]);
+        }
+        if let Some(next) = ids.get(index + 1) {
+            item.set("Next", *next);
+        }
+        if !node.children.is_empty() {
+            let (first, last) = write_children(doc, pages, ids[index], &node.children)?;
+            item.set("First", first);
+            item.set("Last", last);
+            item.set("Count", -(node.children.len() as i64));
+        }
+        doc.objects.insert(ids[index], Object::Dictionary(item));
+    }
+    Ok((ids[0], ids[ids.len() - 1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{pdf::SectionHeading, utils::Language};
+
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn outline_tree_test() {
+        let locations = [
+            (None, "Table of Content", 0),
+            (Some(0), "Table 14.1.1: Demographic", 1),
+            (Some(1), "Table 14.2.8.3.1: EQ-5D-5L", 3),
+            (Some(2), "Table 14.2.8.3.2: EQ-5D-5L Index", 4),
+            (Some(3), "Listing 16.2.1: Disposition", 6),
+        ]
+        .into_iter()
+        .map(|(id, title, page)| Location {
+            id,
+            title: title.into(),
+            page,
+            path: Path::new("").into(),
+            divider: false,
+            attachment: false,
+            ..Default::default()
+        })
+        .collect::<Vec<Location>>();
+        let mut setting = OutlineSetting {
+            nested: true,
+            headings: vec![SectionHeading {
+                number: "14.1".into(),
+                title: "Demographic Data".into(),
+            }],
+            ..Default::default()
+        };
+        let tree = outline_tree(&locations, &setting, &Language::EN.catalog());
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree[1].title, "14");
+        assert_eq!(tree[1].page, 1);
+        assert_eq!(tree[1].children[0].title, "14.1 Demographic Data");
+        let section = &tree[1].children[1].children[0];
+        assert_eq!(section.title, "14.2.8");
+        assert_eq!(section.page, 3);
+        assert_eq!(section.children.len(), 2);
+
+        setting.group_by_type = true;
+        let tree = outline_tree(&locations, &setting, &Language::EN.catalog());
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree[1].title, "Tables");
+        assert_eq!(tree[2].title, "Listings");
+        assert_eq!(tree[2].children[0].children[0].title, "16.2");
+
+        setting.nested = false;
+        assert_eq!(
+            outline_tree(&locations, &setting, &Language::EN.catalog()).len(),
+            5
+        );
+
+        let mut locations = locations;
+        locations.insert(
+            1,
+            Location {
+                id: Some(4),
+                title: "Safety Analysis".into(),
+                page: 1,
+                path: Path::new("").into(),
+                divider: true,
+                attachment: false,
+                ..Default::default()
+            },
+        );
+        let tree = outline_tree(&locations, &setting, &Language::EN.catalog());
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[1].title, "Safety Analysis");
+        assert_eq!(tree[1].children.len(), 4);
+    }
+
+    #[test]
+    fn toc_items_test() {
+        let mut locations = [
+            (0, "Table 14.1.1: Demographic", 0),
+            (1, "Table 14.1.2: Baseline", 2),
+            (2, "Table 14.2.1: Efficacy", 3),
+            (3, "Listing 16.2.1: Disposition", 6),
+        ]
+        .into_iter()
+        .map(|(id, title, page)| Location {
+            id: Some(id),
+            title: title.into(),
+            page,
+            ..Default::default()
+        })
+        .collect::<Vec<Location>>();
+        locations[3].group = Some("Listings".into());
+        let setting = OutlineSetting {
+            nested: true,
+            depth: 2,
+            ..Default::default()
+        };
+        let tree = outline_tree(&locations, &setting, &Language::EN.catalog());
+        assert_eq!(tree[1].title, "Listings");
+        assert_eq!(tree[1].id, Some(3));
+
+        let items = toc_items(&locations, &tree, &TocLevelSetting::default(), 10);
+        let summary = items
+            .iter()
+            .map(|i| (i.title.as_str(), i.level, i.heading, i.last_page))
+            .collect::<Vec<(&str, usize, bool, Option<usize>)>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("14", 0, true, Some(5)),
+                ("14.1", 1, true, Some(2)),
+                ("Table 14.1.1: Demographic", 2, false, None),
+                ("Table 14.1.2: Baseline", 2, false, None),
+                ("14.2", 1, true, Some(5)),
+                ("Table 14.2.1: Efficacy", 2, false, None),
+                ("Listings", 0, true, Some(9)),
+                ("16", 1, true, Some(9)),
+                ("16.2", 2, true, Some(9)),
+                ("Listing 16.2.1: Disposition", 3, false, None),
+            ]
+        );
+        assert_eq!(items[0].id, Some(0));
+    }
+}
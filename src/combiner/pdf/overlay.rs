@@ -0,0 +1,305 @@
+use crate::config::utils::Language;
+use anyhow::anyhow;
+use lopdf::{
+    content::{Content, Operation},
+    dictionary, Dictionary, Document, Object, ObjectId, Stream, StringFormat,
+};
+
+/// cjk font predefined by pdf viewers, text in this font is encoded as UCS-2 and no need to embed
+struct CJKFont {
+    name: &'static str,
+    encoding: &'static str,
+    /// character collection of Adobe, such as "GB1"
+    ordering: &'static str,
+    supplement: i64,
+}
+
+impl CJKFont {
+    /// korean and japanese are not covered by chinese fonts, other languages fallback to chinese
+    fn of(language: &Language) -> Self {
+        match language {
+            Language::KR => CJKFont {
+                name: "HYSMyeongJo-Medium",
+                encoding: "UniKS-UCS2-H",
+                ordering: "Korea1",
+                supplement: 1,
+            },
+            Language::JP => CJKFont {
+                name: "KozMinPro-Regular",
+                encoding: "UniJIS-UCS2-H",
+                ordering: "Japan1",
+                supplement: 2,
+            },
+            _ => CJKFont {
+                name: "STSong-Light",
+                encoding: "UniGB-UCS2-H",
+                ordering: "GB1",
+                supplement: 2,
+            },
+        }
+    }
+}
+
+/// page as it is displayed in viewer, width and height are swapped for rotated page
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisualPage {
+    pub width: f32,
+    pub height: f32,
+    /// transformation from visual coordinates to user space of page
+    matrix: [f32; 6],
+}
+
+impl VisualPage {
+    pub fn new(doc: &Document, page_id: ObjectId) -> anyhow::Result<Self> {
+        let page = doc.get_dictionary(page_id)?;
+        let page_box = match page.get(b"CropBox") {
+            Ok(crop_box) => crop_box,
+            Err(_) => page.get(b"MediaBox")?,
+        };
+        let page_box = doc
+            .dereference(page_box)?
+            .1
+            .as_array()?
+            .iter()
+            .map(|n| n.as_float())
+            .collect::<Result<Vec<f32>, _>>()?;
+        if page_box.len() != 4 {
+            return Err(anyhow!("invalid page box of page {:?}", page_id));
+        }
+        let (x0, y0) = (page_box[0].min(page_box[2]), page_box[1].min(page_box[3]));
+        let (x1, y1) = (page_box[0].max(page_box[2]), page_box[1].max(page_box[3]));
+        let rotate = page
+            .get(b"Rotate")
+            .and_then(Object::as_i64)
+            .unwrap_or(0)
+            .rem_euclid(360);
+        let (width, height) = (x1 - x0, y1 - y0);
+        Ok(match rotate {
+            90 => VisualPage::from_matrix(height, width, [0.0, 1.0, -1.0, 0.0, x1, y0]),
+            180 => VisualPage::from_matrix(width, height, [-1.0, 0.0, 0.0, -1.0, x1, y1]),
+            270 => VisualPage::from_matrix(height, width, [0.0, -1.0, 1.0, 0.0, x0, y1]),
+            _ => VisualPage::from_matrix(width, height, [1.0, 0.0, 0.0, 1.0, x0, y0]),
+        })
+    }
+
+    fn from_matrix(width: f32, height: f32, matrix: [f32; 6]) -> Self {
+        VisualPage {
+            width,
+            height,
+            matrix,
+        }
+    }
+
+    /// point in user space of page from visual coordinates
+    pub fn point(&self, x: f32, y: f32) -> (f32, f32) {
+        let [a, b, c, d, e, f] = self.matrix;
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    /// transformation from user space of page to visual coordinates
+    pub fn inverse(&self) -> [f32; 6] {
+        let [a, b, c, d, e, f] = self.matrix;
+        let det = a * d - b * c;
+        [
+            d / det,
+            -b / det,
+            -c / det,
+            a / det,
+            (c * f - d * e) / det,
+            (b * e - a * f) / det,
+        ]
+    }
+
+    /// operation to switch from user space into visual coordinates, origin at bottom left of visual page
+    pub fn transform(&self) -> Operation {
+        Operation::new("cm", self.matrix.iter().map(|n| (*n).into()).collect())
+    }
+}
+
+/// font used to draw text on existing pages
+#[derive(Debug, Clone)]
+pub struct OverlayFont {
+    /// resource name of font in page, suffixed by object number to avoid clashing with other overlays
+    pub name: String,
+    pub size: f32,
+    cjk: bool,
+    id: ObjectId,
+}
+
+impl OverlayFont {
+    /// create a standard 14 font, such as "Helvetica", text containing non-ascii characters will
+    /// fallback to the predefined cjk font of language
+    pub fn new(
+        doc: &mut Document,
+        prefix: &str,
+        base_font: &str,
+        size: f32,
+        text: &str,
+        language: &Language,
+    ) -> Self {
+        let cjk = !text.is_ascii();
+        let font = if cjk {
+            let cjk_font = CJKFont::of(language);
+            dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type0",
+                "BaseFont" => cjk_font.name,
+                "Encoding" => cjk_font.encoding,
+                "DescendantFonts" => vec![Object::Dictionary(dictionary! {
+                    "Type" => "Font",
+                    "Subtype" => "CIDFontType0",
+                    "BaseFont" => cjk_font.name,
+                    "CIDSystemInfo" => dictionary! {
+                        "Registry" => Object::string_literal("Adobe"),
+                        "Ordering" => Object::string_literal(cjk_font.ordering),
+                        "Supplement" => cjk_font.supplement,
+                    },
+                    "FontDescriptor" => dictionary! {
+                        "Type" => "FontDescriptor",
+                        "FontName" => cjk_font.name,
+                        "Flags" => 6,
+                        "FontBBox" => vec![(-25).into(), (-254).into(), 1000.into(), 880.into()],
+                        "ItalicAngle" => 0,
+                        "Ascent" => 880,
+                        "Descent" => -120,
+                        "CapHeight" => 880,
+                        "StemV" => 93,
+                    },
+                })],
+            }
+        } else {
+            dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => base_font,
+                "Encoding" => "WinAnsiEncoding",
+            }
+        };
+        let id = doc.add_object(font);
+        OverlayFont {
+            name: format!("{}{}", prefix, id.0),
+            size,
+            cjk,
+            id,
+        }
+    }
+
+    /// encode text as operand of "Tj"
+    pub fn encode(&self, text: &str) -> Object {
+        if self.cjk {
+            let mut bytes = vec![];
+            text.encode_utf16()
+                .for_each(|c| bytes.extend(c.to_be_bytes()));
+            Object::String(bytes, StringFormat::Hexadecimal)
+        } else {
+            Object::string_literal(text)
+        }
+    }
+
+    /// approximate width of text, cjk characters take a full em and others about half of it
+    pub fn text_width(&self, text: &str) -> f32 {
+        text.chars()
+            .map(|c| if c.is_ascii() { 0.55 } else { 1.0 })
+            .sum::<f32>()
+            * self.size
+    }
+}
+
+/// draw operations on top of existing content, content of page is wrapped in its own graphics state,
+/// so that the operations always start from default graphics state
+pub fn overlay(
+    doc: &mut Document,
+    page_id: ObjectId,
+    font: Option<&OverlayFont>,
+    operations: Vec<Operation>,
+) -> anyhow::Result<()> {
+    if let Some(font) = font {
+        add_resource(doc, page_id, b"Font", &font.name, font.id)?;
+    }
+    let mut contents = match doc.get_dictionary(page_id)?.get(b"Contents") {
+        Ok(Object::Reference(id)) => vec![Object::Reference(*id)],
+        Ok(Object::Array(contents)) => contents.clone(),
+        _ => vec![],
+    };
+    let save = doc.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec()));
+    let mut content = b"Q\n".to_vec();
+    content.extend(Content { operations }.encode()?);
+    let overlay = doc.add_object(Stream::new(Dictionary::new(), content));
+    contents.insert(0, Object::Reference(save));
+    contents.push(Object::Reference(overlay));
+    doc.get_dictionary_mut(page_id)?.set("Contents", contents);
+    Ok(())
+}
+
+/// register resource such as font or graphics state in page resources, referenced dictionary is resolved
+pub fn add_resource(
+    doc: &mut Document,
+    page_id: ObjectId,
+    category: &[u8],
+    name: &str,
+    id: ObjectId,
+) -> anyhow::Result<()> {
+    let entries_id = {
+        let resources = doc.get_or_create_resources(page_id)?.as_dict_mut()?;
+        if !resources.has(category) {
+            resources.set(category, Dictionary::new());
+        }
+        resources.get(category)?.as_reference().ok()
+    };
+    let entries = match entries_id {
+        Some(entries_id) => doc.get_dictionary_mut(entries_id)?,
+        None => doc
+            .get_or_create_resources(page_id)?
+            .as_dict_mut()?
+            .get_mut(category)?
+            .as_dict_mut()?,
+    };
+    entries.set(name.as_bytes(), Object::Reference(id));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn visual_page_test() -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.5");
+        let portrait = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        });
+        let rotated = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            "Rotate" => 90,
+        });
+        let page = VisualPage::new(&doc, portrait)?;
+        assert_eq!((page.width, page.height), (595.0, 842.0));
+        let page = VisualPage::new(&doc, rotated)?;
+        assert_eq!((page.width, page.height), (842.0, 595.0));
+        assert_eq!(page.matrix, [0.0, 1.0, -1.0, 0.0, 595.0, 0.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn overlay_font_test() -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.5");
+        let font = OverlayFont::new(&mut doc, "F", "Helvetica", 9.0, "Page 1", &Language::KR);
+        assert_eq!(
+            doc.get_dictionary(font.id)?.get(b"Subtype")?.as_name()?,
+            b"Type1"
+        );
+        let font = OverlayFont::new(&mut doc, "F", "Helvetica", 9.0, "페이지 1", &Language::KR);
+        let dict = doc.get_dictionary(font.id)?;
+        assert_eq!(dict.get(b"BaseFont")?.as_name()?, b"HYSMyeongJo-Medium");
+        assert_eq!(dict.get(b"Encoding")?.as_name()?, b"UniKS-UCS2-H");
+        let descendant = dict.get(b"DescendantFonts")?.as_array()?[0].as_dict()?;
+        let ordering = descendant
+            .get(b"CIDSystemInfo")?
+            .as_dict()?
+            .get(b"Ordering")?
+            .as_str()?;
+        assert_eq!(ordering, b"Korea1");
+        Ok(())
+    }
+}
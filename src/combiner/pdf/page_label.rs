@@ -0,0 +1,71 @@
+use super::merge::text_string;
+use lopdf::{dictionary, Document, Object};
+
+/// write /PageLabels number tree, so that page number shown by viewer matches page number in toc
+///
+/// cover pages are labeled by cover title, toc pages use lowercase roman numerals,
+/// and outputs use arabic numerals starting from 1
+pub fn write_page_labels(
+    doc: &mut Document,
+    cover_pages: usize,
+    toc_pages: usize,
+    cover_label: &str,
+) -> anyhow::Result<()> {
+    let mut nums: Vec<Object> = vec![];
+    if cover_pages > 0 {
+        nums.push(Object::Integer(0));
+        nums.push(Object::Dictionary(dictionary! {
+            "P" => text_string(cover_label),
+        }));
+    }
+    if toc_pages > 0 {
+        nums.push(Object::Integer(cover_pages as i64));
+        nums.push(Object::Dictionary(dictionary! {
+            "S" => "r",
+        }));
+    }
+    nums.push(Object::Integer((cover_pages + toc_pages) as i64));
+    nums.push(Object::Dictionary(dictionary! {
+        "S" => "D",
+        "St" => 1,
+    }));
+    doc.catalog_mut()?.set(
+        "PageLabels",
+        dictionary! {
+            "Nums" => nums,
+        },
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn page_labels_test() -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.5");
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog" });
+        doc.trailer.set("Root", catalog_id);
+        write_page_labels(&mut doc, 1, 2, "Cover")?;
+        let nums = doc
+            .catalog()?
+            .get(b"PageLabels")?
+            .as_dict()?
+            .get(b"Nums")?
+            .as_array()?;
+        assert_eq!(nums.len(), 6);
+        assert_eq!(nums[2], Object::Integer(1));
+        assert_eq!(nums[4], Object::Integer(3));
+
+        write_page_labels(&mut doc, 0, 1, "Cover")?;
+        let nums = doc
+            .catalog()?
+            .get(b"PageLabels")?
+            .as_dict()?
+            .get(b"Nums")?
+            .as_array()?;
+        assert_eq!(nums.len(), 4);
+        assert_eq!(nums[0], Object::Integer(0));
+        Ok(())
+    }
+}
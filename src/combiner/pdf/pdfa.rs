@@ -0,0 +1,427 @@
+use crate::config::pdf::PDFAConformance;
+use lopdf::{
+    dictionary, xref::XrefType, Dictionary, Document, Object, ObjectId, Stream, StringFormat,
+};
+use sha2::{Digest, Sha256};
+
+const OUTPUT_CONDITION: &str = "sRGB IEC61966-2.1";
+/// PDF/A requires a comment line with at least 4 bytes greater than 127 right after the header,
+/// lopdf writes it from the binary mark of document
+const BINARY_MARK: [u8; 4] = [0xE2, 0xE3, 0xCF, 0xD3];
+/// annotation flags, print must be set and hidden, invisible and no view must not be set
+const ANNOT_INVISIBLE: i64 = 1;
+const ANNOT_HIDDEN: i64 = 2;
+const ANNOT_PRINT: i64 = 4;
+const ANNOT_NO_VIEW: i64 = 32;
+
+/// make document conform to PDF/A, features forbidden by PDF/A are removed,
+/// return violations which could not be fixed, such as fonts not embedded
+///
+/// XMP metadata with PDF/A identification is written separately by `metadata::write_metadata`
+pub fn convert(doc: &mut Document, conformance: &PDFAConformance) -> anyhow::Result<Vec<String>> {
+    let mut violations = vec![];
+    let part_one = conformance.eq(&PDFAConformance::A1B);
+    doc.version = conformance.version().into();
+    doc.binary_mark = BINARY_MARK.to_vec();
+    doc.reference_table.cross_reference_type = XrefType::CrossReferenceTable;
+
+    if doc.trailer.has(b"Encrypt") {
+        violations.push("document is encrypted".into());
+    }
+    if !doc.trailer.has(b"ID") {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{:?}", doc.trailer));
+        hasher.update(doc.objects.len().to_be_bytes());
+        let id = hasher.finalize()[0..16].to_vec();
+        doc.trailer.set(
+            "ID",
+            vec![
+                Object::String(id.clone(), StringFormat::Hexadecimal),
+                Object::String(id, StringFormat::Hexadecimal),
+            ],
+        );
+    }
+
+    let open_action_allowed = doc
+        .catalog()?
+        .get(b"OpenAction")
+        .map(|action| is_allowed_action(doc, action))
+        .unwrap_or(true);
+    let profile = doc
+        .add_object(Stream::new(dictionary! { "N" => 3 }, srgb_profile()).with_compression(true));
+    let catalog = doc.catalog_mut()?;
+    catalog.set(
+        "OutputIntents",
+        vec![Object::Dictionary(dictionary! {
+            "Type" => "OutputIntent",
+            "S" => "GTS_PDFA1",
+            "OutputConditionIdentifier" => Object::string_literal(OUTPUT_CONDITION),
+            "Info" => Object::string_literal(OUTPUT_CONDITION),
+            "DestOutputProfile" => profile,
+        })],
+    );
+    catalog.remove(b"AA");
+    if part_one {
+        catalog.remove(b"OCProperties");
+    }
+    if let Ok(Object::Dictionary(names)) = catalog.get_mut(b"Names") {
+        names.remove(b"JavaScript");
+        if part_one && names.has(b"EmbeddedFiles") {
+            violations.push("embedded files are not allowed in PDF/A-1".into());
+        }
+    }
+    if !open_action_allowed {
+        catalog.remove(b"OpenAction");
+    }
+
+    let ids = doc.objects.keys().cloned().collect::<Vec<ObjectId>>();
+    for id in ids {
+        let is_font =
+            matches!(doc.get_object(id), Ok(Object::Dictionary(d)) if d.has_type(b"Font"));
+        if is_font {
+            if let Some(font) = not_embedded_font(doc, id) {
+                let violation = format!("font {} is not embedded", font);
+                if !violations.contains(&violation) {
+                    violations.push(violation);
+                }
+            }
+            continue;
+        }
+        let action_allowed = match doc.get_object(id) {
+            Ok(Object::Dictionary(dict)) => dict
+                .get(b"A")
+                .map(|action| is_allowed_action(doc, action))
+                .unwrap_or(true),
+            _ => true,
+        };
+        match doc.get_object_mut(id)? {
+            Object::Dictionary(dict) => {
+                fix_dictionary(dict, id, part_one, action_allowed, &mut violations)
+            }
+            Object::Stream(stream) => {
+                fix_dictionary(&mut stream.dict, id, part_one, true, &mut violations);
+                if part_one && is_filtered_by(&stream.dict, b"LZWDecode") {
+                    violations.push(format!("object {:?} uses LZW compression", id));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(violations)
+}
+
+/// fix dictionary in place according its type, report what could not be fixed
+fn fix_dictionary(
+    dict: &mut Dictionary,
+    id: ObjectId,
+    part_one: bool,
+    action_allowed: bool,
+    violations: &mut Vec<String>,
+) {
+    dict.remove(b"AA");
+    // type is optional for annotations and graphics states
+    let is_annotation = dict.has_type(b"Annot") || (dict.has(b"Rect") && dict.has(b"Subtype"));
+    let is_graphics_state = dict.has_type(b"ExtGState")
+        || (!dict.has(b"Subtype")
+            && [b"TR".as_slice(), b"TR2", b"SMask", b"CA", b"ca"]
+                .iter()
+                .any(|key| dict.has(key)));
+    if is_annotation {
+        let subtype = dict
+            .get(b"Subtype")
+            .and_then(Object::as_name)
+            .map(String::from_utf8_lossy)
+            .unwrap_or_default();
+        if ["Sound", "Movie", "Screen", "3D"].contains(&subtype.as_ref())
+            || (part_one && subtype.eq("FileAttachment"))
+        {
+            violations.push(format!(
+                "annotation {} of object {:?} is not allowed",
+                subtype, id
+            ));
+        }
+        if subtype.ne("Popup") {
+            let flags = dict.get(b"F").and_then(Object::as_i64).unwrap_or(0);
+            let flags = (flags | ANNOT_PRINT) & !(ANNOT_INVISIBLE | ANNOT_HIDDEN | ANNOT_NO_VIEW);
+            dict.set("F", flags);
+        }
+        if part_one {
+            dict.remove(b"CA");
+        }
+        if !action_allowed {
+            dict.remove(b"A");
+        }
+    }
+    if is_graphics_state {
+        dict.remove(b"TR");
+        if let Ok(Object::Name(name)) = dict.get(b"TR2") {
+            if name.ne(b"Default") {
+                dict.remove(b"TR2");
+            }
+        }
+        if part_one {
+            let soft_mask =
+                matches!(dict.get(b"SMask"), Ok(Object::Name(name)) if name.eq(b"None"));
+            if dict.has(b"SMask") && !soft_mask {
+                violations.push(format!(
+                    "soft mask of object {:?} is not allowed in PDF/A-1",
+                    id
+                ));
+            }
+            for key in [b"CA", b"ca"] {
+                if dict.get(key).and_then(Object::as_float).unwrap_or(1.0) < 1.0 {
+                    violations.push(format!(
+                        "transparency of object {:?} is not allowed in PDF/A-1",
+                        id
+                    ));
+                }
+            }
+        }
+    }
+    let subtype = dict
+        .get(b"Subtype")
+        .and_then(Object::as_name)
+        .map(|name| String::from_utf8_lossy(name).to_string())
+        .unwrap_or_default();
+    if subtype.eq("Image") {
+        dict.remove(b"Interpolate");
+        dict.remove(b"Alternates");
+        dict.remove(b"OPI");
+        if part_one && dict.has(b"SMask") {
+            violations.push(format!(
+                "image {:?} with soft mask is not allowed in PDF/A-1",
+                id
+            ));
+        }
+        if let Ok(Object::Name(name)) = dict.get(b"ColorSpace") {
+            if name.eq(b"DeviceCMYK") {
+                violations.push(format!(
+                    "image {:?} uses DeviceCMYK without CMYK output intent",
+                    id
+                ));
+            }
+        }
+    }
+    if subtype.eq("Form") {
+        dict.remove(b"OPI");
+        dict.remove(b"PS");
+    }
+    // transparency group of page or form is only a hint of blending and is dropped in PDF/A-1
+    if part_one && (dict.has_type(b"Page") || subtype.eq("Form")) {
+        if let Ok(Object::Dictionary(group)) = dict.get(b"Group") {
+            if group
+                .get(b"S")
+                .and_then(Object::as_name)
+                .unwrap_or(b"")
+                .eq(b"Transparency")
+            {
+                dict.remove(b"Group");
+            }
+        }
+    }
+}
+
+/// actions except JavaScript, Launch, Sound, Movie and forms are allowed
+fn is_allowed_action(doc: &Document, action: &Object) -> bool {
+    let action = match doc.dereference(action) {
+        Ok((_, Object::Dictionary(action))) => action,
+        _ => return true,
+    };
+    let kind = action
+        .get(b"S")
+        .and_then(Object::as_name)
+        .map(String::from_utf8_lossy)
+        .unwrap_or_default();
+    ![
+        "JavaScript",
+        "Launch",
+        "Sound",
+        "Movie",
+        "ResetForm",
+        "ImportData",
+        "Hide",
+        "SetOCGState",
+        "Rendition",
+        "Trans",
+        "GoTo3DView",
+    ]
+    .contains(&kind.as_ref())
+}
+
+fn is_filtered_by(dict: &Dictionary, filter: &[u8]) -> bool {
+    match dict.get(b"Filter") {
+        Ok(Object::Name(name)) => name.eq(filter),
+        Ok(Object::Array(names)) => names
+            .iter()
+            .any(|name| name.as_name().map(|n| n.eq(filter)).unwrap_or(false)),
+        _ => false,
+    }
+}
+
+/// return base font name if font program is not embedded, type3 fonts are always fine
+fn not_embedded_font(doc: &Document, font_id: ObjectId) -> Option<String> {
+    let font = doc.get_dictionary(font_id).ok()?;
+    let name = font
+        .get(b"BaseFont")
+        .and_then(Object::as_name)
+        .map(|name| String::from_utf8_lossy(name).to_string())
+        .unwrap_or("unknown".into());
+    let descriptor = match font.get(b"Subtype").and_then(Object::as_name).ok()? {
+        b"Type3" => return None,
+        b"Type0" => {
+            let descendants = font
+                .get_deref(b"DescendantFonts", doc)
+                .ok()?
+                .as_array()
+                .ok()?;
+            let descendant = doc
+                .dereference(descendants.first()?)
+                .ok()?
+                .1
+                .as_dict()
+                .ok()?;
+            descendant.get_deref(b"FontDescriptor", doc)
+        }
+        _ => font.get_deref(b"FontDescriptor", doc),
+    };
+    match descriptor.and_then(Object::as_dict) {
+        Ok(descriptor)
+            if descriptor.has(b"FontFile")
+                || descriptor.has(b"FontFile2")
+                || descriptor.has(b"FontFile3") =>
+        {
+            None
+        }
+        _ => Some(name),
+    }
+}
+
+/// ICC v2 display profile of sRGB, tone curves are sampled from the sRGB transfer function
+fn srgb_profile() -> Vec<u8> {
+    fn s15_fixed16(value: f64) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+    fn xyz(x: f64, y: f64, z: f64) -> Vec<u8> {
+        let mut data = b"XYZ \0\0\0\0".to_vec();
+        [x, y, z].iter().for_each(|v| data.extend(s15_fixed16(*v)));
+        data
+    }
+    fn curve() -> Vec<u8> {
+        let samples = 1024u32;
+        let mut data = b"curv\0\0\0\0".to_vec();
+        data.extend(samples.to_be_bytes());
+        for i in 0..samples {
+            let x = i as f64 / (samples - 1) as f64;
+            let y = if x <= 0.04045 {
+                x / 12.92
+            } else {
+                ((x + 0.055) / 1.055).powf(2.4)
+            };
+            data.extend(((y * 65535.0).round() as u16).to_be_bytes());
+        }
+        data
+    }
+    let description = {
+        let mut data = b"desc\0\0\0\0".to_vec();
+        data.extend((OUTPUT_CONDITION.len() as u32 + 1).to_be_bytes());
+        data.extend(OUTPUT_CONDITION.as_bytes());
+        data.push(0);
+        // empty unicode and script code descriptions
+        data.extend([0u8; 8 + 3 + 67]);
+        data
+    };
+    let copyright = {
+        let mut data = b"text\0\0\0\0".to_vec();
+        data.extend(b"No copyright, use freely\0");
+        data
+    };
+    let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"desc", description),
+        (b"cprt", copyright),
+        (b"wtpt", xyz(0.9642, 1.0, 0.8249)),
+        (b"rXYZ", xyz(0.4360747, 0.2225045, 0.0139322)),
+        (b"gXYZ", xyz(0.3850649, 0.7168786, 0.0971045)),
+        (b"bXYZ", xyz(0.1430804, 0.0606169, 0.7141733)),
+        (b"rTRC", curve()),
+        (b"gTRC", curve()),
+        (b"bTRC", curve()),
+    ];
+
+    let data_start = 128 + 4 + 12 * tags.len();
+    let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+    let mut data = vec![];
+    for (signature, tag) in tags.iter() {
+        table.extend(*signature);
+        table.extend(((data_start + data.len()) as u32).to_be_bytes());
+        table.extend((tag.len() as u32).to_be_bytes());
+        data.extend(tag);
+        // tag data starts on a 4-byte boundary
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+    }
+
+    let size = 128 + table.len() + data.len();
+    let mut header = vec![];
+    header.extend((size as u32).to_be_bytes());
+    header.extend([0u8; 4]); // preferred cmm
+    header.extend([0x02, 0x10, 0x00, 0x00]); // version 2.1
+    header.extend(b"mntrRGB XYZ ");
+    header.extend([0x07, 0xE8, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0]); // 2024-01-01 00:00:00
+    header.extend(b"acsp");
+    header.extend([0u8; 24]); // platform, flags, manufacturer, model and attributes
+    header.extend([0u8; 4]); // perceptual rendering intent
+    header.extend(s15_fixed16(0.9642));
+    header.extend(s15_fixed16(1.0));
+    header.extend(s15_fixed16(0.8249));
+    header.extend([0u8; 48]); // creator and reserved
+    header.extend(table);
+    header.extend(data);
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn binary_mark_test() -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.7");
+        doc.binary_mark = vec![];
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog" });
+        doc.trailer.set("Root", catalog);
+        convert(&mut doc, &PDFAConformance::A1B)?;
+        assert_eq!(doc.version, PDFAConformance::A1B.version());
+        let mut output = vec![];
+        doc.save_to(&mut output)?;
+        let mut lines = output.split(|byte| *byte == b'\n');
+        assert_eq!(
+            lines.next(),
+            Some(format!("%PDF-{}", PDFAConformance::A1B.version()).as_bytes())
+        );
+        assert_eq!(
+            lines.next(),
+            Some([b"%".as_slice(), &BINARY_MARK].concat().as_slice())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn srgb_profile_test() {
+        let profile = srgb_profile();
+        assert_eq!(
+            u32::from_be_bytes(profile[0..4].try_into().unwrap()) as usize,
+            profile.len()
+        );
+        assert_eq!(&profile[36..40], b"acsp");
+        // every tag is inside the profile and aligned
+        let count = u32::from_be_bytes(profile[128..132].try_into().unwrap()) as usize;
+        assert_eq!(count, 9);
+        for index in 0..count {
+            let entry = 132 + index * 12;
+            let offset = u32::from_be_bytes(profile[entry + 4..entry + 8].try_into().unwrap());
+            let size = u32::from_be_bytes(profile[entry + 8..entry + 12].try_into().unwrap());
+            assert_eq!(offset % 4, 0);
+            assert!((offset + size) as usize <= profile.len());
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::config::pdf::SplitSetting;
+use lopdf::{dictionary, Document, Object};
+use std::collections::HashMap;
+
+/// group outputs into volumes, each output is given as its pages and bytes
+///
+/// an output exceeding limits by itself still forms a volume of its own, returns indexes of outputs in every volume
+pub fn volumes(outputs: &[(usize, u64)], setting: &SplitSetting) -> Vec<Vec<usize>> {
+    let max_pages = setting.max_pages.unwrap_or(usize::MAX);
+    let max_bytes = setting.max_bytes().unwrap_or(u64::MAX);
+    let mut result: Vec<Vec<usize>> = vec![];
+    let (mut pages, mut bytes) = (0, 0);
+    for (index, (output_pages, output_bytes)) in outputs.iter().enumerate() {
+        match result.last_mut() {
+            Some(volume)
+                if pages + output_pages <= max_pages && bytes + output_bytes <= max_bytes =>
+            {
+                volume.push(index);
+                pages += output_pages;
+                bytes += output_bytes;
+            }
+            _ => {
+                result.push(vec![index]);
+                pages = *output_pages;
+                bytes = *output_bytes;
+            }
+        }
+    }
+    result
+}
+
+/// point toc links of index to pages in other files, targets map destination name to file name and page index
+pub fn link_volumes(
+    doc: &mut Document,
+    targets: &HashMap<String, (String, usize)>,
+) -> anyhow::Result<()> {
+    let annotations = doc
+        .objects
+        .iter()
+        .filter_map(|(id, object)| match object.as_dict() {
+            Ok(dict) if dict.has_type(b"Annot") => {
+                match dict.get(b"Dest").and_then(Object::as_name) {
+                    Ok(name) => targets
+                        .get(String::from_utf8_lossy(name).as_ref())
+                        .map(|target| (*id, target.clone())),
+                    Err(_) => None,
+                }
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    for (id, (file, page)) in annotations {
+        let annotation = doc.get_dictionary_mut(id)?;
+        annotation.remove(b"Dest");
+        annotation.set("A", volume_action(&file, page));
+    }
+    Ok(())
+}
+
+/// action opening page index `page` of another file
+pub fn volume_action(file: &str, page: usize) -> Object {
+    Object::Dictionary(dictionary! {
+        "S" => "GoToR",
+        "F" => Object::string_literal(file),
+        "D" => vec![
+            (page as i64).into(),
+            Object::Name(b"XYZ".into()),
+            Object::Null,
+            Object::Null,
+            Object::Null,
+        ],
+        "NewWindow" => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn volumes_test() {
+        let outputs = [(3, 100), (4, 100), (2, 100), (10, 100), (1, 100)];
+        let setting = SplitSetting {
+            max_pages: Some(8),
+            ..Default::default()
+        };
+        assert_eq!(
+            volumes(&outputs, &setting),
+            vec![vec![0, 1], vec![2], vec![3], vec![4]]
+        );
+        let setting = SplitSetting {
+            max_size: Some(250.0 / 1024.0 / 1024.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            volumes(&outputs, &setting),
+            vec![vec![0, 1], vec![2, 3], vec![4]]
+        );
+        assert_eq!(volumes(&outputs, &SplitSetting::default()).len(), 1);
+    }
+}
@@ -0,0 +1,89 @@
+use super::{
+    merge::page_ids,
+    overlay::{overlay, OverlayFont, VisualPage},
+};
+use crate::config::{
+    combine::PDFFile,
+    pdf::{StampPosition, StampSetting},
+    utils::Language,
+};
+use lopdf::{content::Operation, Document};
+
+const STAMP_FONT: &str = "FusionStamp";
+
+/// stamp page number on every page of outputs, pages are counted from the first page of the first output,
+/// cover and toc are not stamped
+///
+/// `page_actual` and `page_display` of files must be updated before stamping
+pub fn stamp(
+    doc: &mut Document,
+    files: &[PDFFile],
+    setting: &StampSetting,
+    language: &Language,
+) -> anyhow::Result<()> {
+    let first = match files.first() {
+        Some(file) => file,
+        None => return Ok(()),
+    };
+    let pages = page_ids(doc);
+    let total = pages.len().saturating_sub(first.page_actual);
+    let font = OverlayFont::new(
+        doc,
+        STAMP_FONT,
+        &setting.font,
+        setting.font_size,
+        &setting.template,
+        language,
+    );
+    for (index, page_id) in pages.range(first.page_actual..) {
+        let page = index - first.page_actual + first.page_display;
+        let text = setting.text(page, total);
+        let visual = VisualPage::new(doc, *page_id)?;
+        let (x, y) = position(&visual, setting, font.text_width(&text));
+        overlay(
+            doc,
+            *page_id,
+            Some(&font),
+            vec![
+                visual.transform(),
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec![font.name.as_str().into(), font.size.into()]),
+                Operation::new("Td", vec![x.into(), y.into()]),
+                Operation::new("Tj", vec![font.encode(&text)]),
+                Operation::new("ET", vec![]),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// start point of text in visual coordinates
+fn position(page: &VisualPage, setting: &StampSetting, width: f32) -> (f32, f32) {
+    let x = match setting.position {
+        StampPosition::TopLeft | StampPosition::BottomLeft => setting.margin,
+        StampPosition::TopCenter | StampPosition::BottomCenter => (page.width - width) / 2.0,
+        StampPosition::TopRight | StampPosition::BottomRight => page.width - setting.margin - width,
+    };
+    let y = match setting.position {
+        StampPosition::TopLeft | StampPosition::TopCenter | StampPosition::TopRight => {
+            page.height - setting.margin - setting.font_size
+        }
+        _ => setting.margin,
+    };
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn stamp_text_test() {
+        let setting = StampSetting::default();
+        assert_eq!(setting.text(3, 120), "Page 3 of 120");
+        let setting = StampSetting {
+            template: "第 {page} 页 共 {total} 页".into(),
+            ..Default::default()
+        };
+        assert_eq!(setting.text(3, 120), "第 3 页 共 120 页");
+    }
+}
@@ -1,2 +1,6 @@
+pub mod export;
+pub mod font;
+pub mod native;
+pub mod pool;
 pub mod render;
 pub mod template;
@@ -0,0 +1,395 @@
+use super::template::EXPORT_TEMPLATE;
+use crate::{
+    combiner::pdf::location::Location,
+    config::{
+        locale::Catalog,
+        pdf::{PaperSize, TocFormat},
+    },
+};
+use rust_xlsxwriter::{Format, Workbook};
+use serde::Serialize;
+use std::{fs, io::Write, path::Path};
+use tera::{Context, Tera};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+/// margins of rtf and docx in twips, 1 inch
+const MARGIN: i64 = 1440;
+const TWIPS_PER_POINT: f32 = 20.0;
+
+/// entry of exported toc, page is shown the same as in toc of pdf
+#[derive(Debug, Serialize)]
+struct Entry {
+    title: String,
+    page: String,
+    level: usize,
+    heading: bool,
+    /// pdf file of output, empty for headings
+    file: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportData<'a> {
+    lang: &'a str,
+    title: &'a str,
+    toc_headers: &'a (String, String, String, String),
+    entries: &'a [Entry],
+    indent: f32,
+}
+
+/// toc as editable documents, pasted into the report body or used for tracking
+#[derive(Debug, Default)]
+pub struct TocExport {
+    entries: Vec<Entry>,
+    title: String,
+    toc_headers: (String, String, String, String),
+    catalog: Catalog,
+    size: PaperSize,
+    indent: f32,
+}
+
+impl TocExport {
+    pub fn new(items: &[Location]) -> Self {
+        let entries = items
+            .iter()
+            .filter(|item| !item.title.is_empty())
+            .map(|item| Entry {
+                title: item.title.clone(),
+                page: item.page_number(),
+                level: item.level,
+                heading: item.heading || item.divider,
+                file: item
+                    .path
+                    .file_name()
+                    .filter(|_| !item.heading)
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            })
+            .collect();
+        TocExport {
+            entries,
+            indent: 12.0,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_title(&mut self, title: &str) -> &mut Self {
+        self.title = title.to_owned();
+        self
+    }
+
+    pub fn set_toc_headers(&mut self, toc_headers: &(String, String, String, String)) -> &mut Self {
+        self.toc_headers = toc_headers.clone();
+        self
+    }
+
+    /// column names and language tag of exported documents
+    pub fn set_catalog(&mut self, catalog: &Catalog) -> &mut Self {
+        self.catalog = catalog.clone();
+        self
+    }
+
+    /// paper of rtf and docx, which are always portrait
+    pub fn set_size(&mut self, size: &PaperSize) -> &mut Self {
+        self.size = size.clone();
+        self
+    }
+
+    /// indentation of every level in points
+    pub fn set_indent(&mut self, indent: f32) -> &mut Self {
+        self.indent = indent;
+        self
+    }
+
+    pub fn save(&self, format: &TocFormat, dest: &Path) -> anyhow::Result<()> {
+        match format {
+            TocFormat::RTF => fs::write(dest, self.rtf())?,
+            TocFormat::DOCX => self.docx(dest)?,
+            TocFormat::XLSX => self.xlsx(dest)?,
+            TocFormat::HTML => fs::write(dest, self.html()?)?,
+        }
+        Ok(())
+    }
+
+    /// width and height of paper, and position of right tab stop, in twips
+    fn paper(&self) -> (i64, i64, i64) {
+        let (width, height) = self.size.dimensions();
+        let width = (width * TWIPS_PER_POINT).round() as i64;
+        let height = (height * TWIPS_PER_POINT).round() as i64;
+        (width, height, width - MARGIN * 2)
+    }
+
+    fn indent_twips(&self, level: usize) -> i64 {
+        (level as f32 * self.indent * TWIPS_PER_POINT).round() as i64
+    }
+
+    fn rtf(&self) -> String {
+        let (width, height, right) = self.paper();
+        let mut rtf = format!(
+            "{{\\rtf1\\ansi\\deff0{{\\fonttbl{{\\f0\\fnil\\fcharset134 SimSun;}}}}\n\
+             \\paperw{width}\\paperh{height}\\margl{MARGIN}\\margr{MARGIN}\\margt{MARGIN}\\margb{MARGIN}\\fs21\n"
+        );
+        for (left, right_text) in [
+            (&self.toc_headers.0, &self.toc_headers.1),
+            (&self.toc_headers.2, &self.toc_headers.3),
+        ] {
+            if left.is_empty() && right_text.is_empty() {
+                continue;
+            }
+            rtf.push_str(&format!(
+                "\\pard\\tqr\\tx{right} {}\\tab {}\\par\n",
+                rtf_text(left),
+                rtf_text(right_text)
+            ));
+        }
+        rtf.push_str(&format!(
+            "\\pard\\qc\\sb240\\sa240\\b\\fs30 {}\\b0\\fs21\\par\n",
+            rtf_text(&self.title)
+        ));
+        for entry in &self.entries {
+            let (bold, unbold) = if entry.heading {
+                ("\\b ", "\\b0")
+            } else {
+                ("", "")
+            };
+            rtf.push_str(&format!(
+                "\\pard\\li{}\\tqr\\tldot\\tx{right}\\sa60 {bold}{}{unbold}\\tab {}\\par\n",
+                self.indent_twips(entry.level),
+                rtf_text(&entry.title),
+                entry.page
+            ));
+        }
+        rtf.push('}');
+        rtf
+    }
+
+    fn docx(&self, dest: &Path) -> anyhow::Result<()> {
+        let (width, height, right) = self.paper();
+        let mut body = String::new();
+        for (left, right_text) in [
+            (&self.toc_headers.0, &self.toc_headers.1),
+            (&self.toc_headers.2, &self.toc_headers.3),
+        ] {
+            if left.is_empty() && right_text.is_empty() {
+                continue;
+            }
+            body.push_str(&format!(
+                "<w:p><w:pPr><w:tabs><w:tab w:val=\"right\" w:pos=\"{right}\"/></w:tabs></w:pPr>\
+                 {}<w:r><w:tab/></w:r>{}</w:p>",
+                docx_run(left, false),
+                docx_run(right_text, false)
+            ));
+        }
+        body.push_str(&format!(
+            "<w:p><w:pPr><w:jc w:val=\"center\"/><w:spacing w:before=\"240\" w:after=\"240\"/></w:pPr>\
+             <w:r><w:rPr><w:b/><w:sz w:val=\"30\"/></w:rPr><w:t xml:space=\"preserve\">{}</w:t></w:r></w:p>",
+            xml_text(&self.title)
+        ));
+        for entry in &self.entries {
+            body.push_str(&format!(
+                "<w:p><w:pPr><w:tabs><w:tab w:val=\"right\" w:leader=\"dot\" w:pos=\"{right}\"/></w:tabs>\
+                 <w:spacing w:after=\"60\"/><w:ind w:left=\"{}\"/></w:pPr>{}<w:r><w:tab/></w:r>{}</w:p>",
+                self.indent_twips(entry.level),
+                docx_run(&entry.title, entry.heading),
+                docx_run(&entry.page, false)
+            ));
+        }
+        let document = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+             <w:document xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">\
+             <w:body>{body}<w:sectPr><w:pgSz w:w=\"{width}\" w:h=\"{height}\"/>\
+             <w:pgMar w:top=\"{MARGIN}\" w:right=\"{MARGIN}\" w:bottom=\"{MARGIN}\" w:left=\"{MARGIN}\" \
+             w:header=\"720\" w:footer=\"720\" w:gutter=\"0\"/></w:sectPr></w:body></w:document>"
+        );
+        let mut zip = ZipWriter::new(fs::File::create(dest)?);
+        let options = SimpleFileOptions::default();
+        for (name, content) in [
+            ("[Content_Types].xml", DOCX_CONTENT_TYPES),
+            ("_rels/.rels", DOCX_RELS),
+            ("word/document.xml", &document),
+        ] {
+            zip.start_file(name, options)?;
+            zip.write_all(content.as_bytes())?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    fn xlsx(&self, dest: &Path) -> anyhow::Result<()> {
+        let columns = &self.catalog.export_columns;
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(truncate(&self.title, 31))?;
+        let bold = Format::new().set_bold();
+        let mut row = 0;
+        for (left, right) in [
+            (&self.toc_headers.0, &self.toc_headers.1),
+            (&self.toc_headers.2, &self.toc_headers.3),
+        ] {
+            if left.is_empty() && right.is_empty() {
+                continue;
+            }
+            sheet.write_string(row, 0, left)?;
+            sheet.write_string(row, 1, right)?;
+            row += 1;
+        }
+        for (col, column) in [&columns.0, &columns.1, &columns.2].iter().enumerate() {
+            sheet.write_string_with_format(row, col as u16, *column, &bold)?;
+        }
+        for entry in &self.entries {
+            row += 1;
+            let mut format = Format::new().set_indent(entry.level.min(15) as u8);
+            if entry.heading {
+                format = format.set_bold();
+            }
+            sheet.write_string_with_format(row, 0, &entry.title, &format)?;
+            // page ranges of headings are text, single pages are numbers for sorting and formulas
+            match entry.page.parse::<u32>() {
+                Ok(page) => sheet.write_number(row, 1, page)?,
+                Err(_) => sheet.write_string(row, 1, &entry.page)?,
+            };
+            sheet.write_string(row, 2, &entry.file)?;
+        }
+        sheet.set_column_width(0, 80)?;
+        sheet.set_column_width(1, 10)?;
+        sheet.set_column_width(2, 40)?;
+        workbook.save(dest)?;
+        Ok(())
+    }
+
+    fn html(&self) -> anyhow::Result<String> {
+        let data = ExportData {
+            lang: &self.catalog.tag,
+            title: &self.title,
+            toc_headers: &self.toc_headers,
+            entries: &self.entries,
+            indent: self.indent,
+        };
+        Ok(Tera::one_off(
+            EXPORT_TEMPLATE,
+            &Context::from_serialize(&data)?,
+            true,
+        )?)
+    }
+}
+
+const DOCX_CONTENT_TYPES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+<Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+<Override PartName=\"/word/document.xml\" \
+ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>\
+</Types>";
+
+const DOCX_RELS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+<Relationship Id=\"rId1\" \
+Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" \
+Target=\"word/document.xml\"/></Relationships>";
+
+/// escape rtf control characters, characters out of ascii are written as unicode escapes
+fn rtf_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '\\' | '{' | '}' => {
+                result.push('\\');
+                result.push(character);
+            }
+            c if c.is_ascii() => result.push(c),
+            c => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    result.push_str(&format!("\\u{}?", *unit as i16));
+                }
+            }
+        }
+    }
+    result
+}
+
+fn xml_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// run of text with east asian font, empty for empty text
+fn docx_run(text: &str, bold: bool) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    format!(
+        "<w:r><w:rPr><w:rFonts w:eastAsia=\"SimSun\"/>{}</w:rPr><w:t xml:space=\"preserve\">{}</w:t></w:r>",
+        if bold { "<w:b/>" } else { "" },
+        xml_text(text)
+    )
+}
+
+/// worksheet names are at most 31 characters and could not contain some characters
+fn truncate(name: &str, max: usize) -> String {
+    let name = name
+        .chars()
+        .filter(|c| !matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\'))
+        .take(max)
+        .collect::<String>();
+    if name.is_empty() {
+        "TOC".into()
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::utils::Language;
+    use std::path::PathBuf;
+    #[test]
+    fn rtf_text_test() {
+        assert_eq!(rtf_text(r"a{b}\c"), r"a\{b\}\\c");
+        assert_eq!(rtf_text("目录："), r"\u30446?\u24405?\u-230?");
+    }
+
+    #[test]
+    fn export_test() -> anyhow::Result<()> {
+        let items = vec![
+            Location {
+                id: Some(0),
+                title: "14.1 Demographic <Data>".into(),
+                page: 0,
+                heading: true,
+                last_page: Some(4),
+                ..Default::default()
+            },
+            Location {
+                id: Some(0),
+                title: "表 14.1.1: 人口学资料".into(),
+                page: 0,
+                level: 1,
+                path: PathBuf::from("t-14-01-01-dm.pdf"),
+                ..Default::default()
+            },
+        ];
+        let mut export = TocExport::new(&items);
+        export
+            .set_title("Table of Contents")
+            .set_catalog(&Language::EN.catalog())
+            .set_toc_headers(&("Study".into(), "Confidential".into(), "".into(), "".into()));
+        let html = export.html()?;
+        assert!(html.contains("14.1 Demographic &lt;Data&gt;"));
+        assert!(html.contains("padding-left: 12pt;"));
+        assert!(html.contains(">1-5<"));
+        let rtf = export.rtf();
+        assert!(rtf.contains(r"\li240\tqr\tldot\tx9026"));
+
+        let dir = std::env::temp_dir();
+        for format in [TocFormat::DOCX, TocFormat::XLSX] {
+            let dest = dir.join(format!("fusion_toc_export.{}", format.extension()));
+            export.save(&format, &dest)?;
+            let archive = zip::ZipArchive::new(fs::File::open(&dest)?)?;
+            assert!(archive.len() >= 3);
+            fs::remove_file(&dest)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,471 @@
+use anyhow::anyhow;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::Path,
+};
+
+/// tables kept in subset, the ones required by pdf for TrueType fonts in Type0 fonts
+const SUBSET_TABLES: [&[u8; 4]; 9] = [
+    b"cvt ", b"fpgm", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"prep",
+];
+/// flags of composite glyph components
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+/// TrueType font with glyph outlines, the first font of a collection (.ttc) is used
+pub struct TrueTypeFont {
+    data: Vec<u8>,
+    /// offset and length of tables
+    tables: HashMap<[u8; 4], (usize, usize)>,
+    pub name: String,
+    pub units_per_em: u16,
+    pub ascent: i16,
+    pub descent: i16,
+    /// xMin, yMin, xMax, yMax
+    pub bbox: [i16; 4],
+    widths: Vec<u16>,
+    cmap: HashMap<u32, u16>,
+}
+
+impl TrueTypeFont {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or("Font".into());
+        TrueTypeFont::parse(fs::read(path)?, &name)
+    }
+
+    pub fn parse(data: Vec<u8>, name: &str) -> anyhow::Result<Self> {
+        let face = match data.get(0..4) {
+            Some(b"ttcf") => read_u32(&data, 12)? as usize,
+            _ => 0,
+        };
+        let count = read_u16(&data, face + 4)? as usize;
+        let mut tables = HashMap::new();
+        for index in 0..count {
+            let record = face + 12 + index * 16;
+            let mut tag = [0; 4];
+            tag.copy_from_slice(
+                data.get(record..record + 4)
+                    .ok_or(anyhow!("invalid font"))?,
+            );
+            let offset = read_u32(&data, record + 8)? as usize;
+            let length = read_u32(&data, record + 12)? as usize;
+            if offset + length > data.len() {
+                return Err(anyhow!(
+                    "table {} out of font",
+                    String::from_utf8_lossy(&tag)
+                ));
+            }
+            tables.insert(tag, (offset, length));
+        }
+        if !tables.contains_key(b"glyf") {
+            return Err(anyhow!("font {} has no TrueType outlines", name));
+        }
+        let mut font = TrueTypeFont {
+            data,
+            tables,
+            // names in pdf could not contain spaces
+            name: name.chars().filter(|c| c.is_ascii_alphanumeric()).collect(),
+            units_per_em: 1000,
+            ascent: 0,
+            descent: 0,
+            bbox: [0; 4],
+            widths: vec![],
+            cmap: HashMap::new(),
+        };
+        let head = font.table(b"head")?;
+        let units_per_em = read_u16(head, 18)?;
+        let mut bbox = [0; 4];
+        for (index, value) in bbox.iter_mut().enumerate() {
+            *value = read_u16(head, 36 + index * 2)? as i16;
+        }
+        let hhea = font.table(b"hhea")?;
+        let ascent = read_u16(hhea, 4)? as i16;
+        let descent = read_u16(hhea, 6)? as i16;
+        let metrics = read_u16(hhea, 34)? as usize;
+        let glyphs = read_u16(font.table(b"maxp")?, 4)? as usize;
+        let hmtx = font.table(b"hmtx")?;
+        let mut widths = (0..metrics)
+            .map(|index| read_u16(hmtx, index * 4))
+            .collect::<anyhow::Result<Vec<u16>>>()?;
+        // glyphs after the last metric share its advance width
+        let last = widths.last().copied().unwrap_or(0);
+        widths.resize(glyphs.max(metrics), last);
+        font.units_per_em = units_per_em;
+        font.bbox = bbox;
+        font.ascent = ascent;
+        font.descent = descent;
+        font.widths = widths;
+        font.cmap = parse_cmap(font.table(b"cmap")?)?;
+        Ok(font)
+    }
+
+    fn table(&self, tag: &[u8; 4]) -> anyhow::Result<&[u8]> {
+        let (offset, length) = self
+            .tables
+            .get(tag)
+            .ok_or(anyhow!("table {} not found", String::from_utf8_lossy(tag)))?;
+        Ok(&self.data[*offset..*offset + *length])
+    }
+
+    /// glyph of character, 0 (.notdef) if the font does not cover it
+    pub fn glyph(&self, character: char) -> u16 {
+        self.cmap.get(&(character as u32)).copied().unwrap_or(0)
+    }
+
+    /// advance width of glyph in 1/1000 of font size
+    pub fn advance(&self, glyph: u16) -> f32 {
+        let width = self.widths.get(glyph as usize).copied().unwrap_or(0);
+        width as f32 * 1000.0 / self.units_per_em as f32
+    }
+
+    /// width of text in points
+    pub fn width(&self, text: &str, size: f32) -> f32 {
+        text.chars()
+            .map(|c| self.advance(self.glyph(c)))
+            .sum::<f32>()
+            * size
+            / 1000.0
+    }
+
+    /// font program with outlines of given glyphs only, glyph ids are kept so that text needs no re-encoding
+    pub fn subset(&self, glyphs: &BTreeSet<u16>) -> anyhow::Result<Vec<u8>> {
+        let glyf = self.table(b"glyf")?;
+        let head = self.table(b"head")?;
+        let long_loca = read_u16(head, 50)? == 1;
+        let loca = self.table(b"loca")?;
+        let location = |glyph: usize| -> anyhow::Result<(usize, usize)> {
+            Ok(match long_loca {
+                true => (
+                    read_u32(loca, glyph * 4)? as usize,
+                    read_u32(loca, glyph * 4 + 4)? as usize,
+                ),
+                false => (
+                    read_u16(loca, glyph * 2)? as usize * 2,
+                    read_u16(loca, glyph * 2 + 2)? as usize * 2,
+                ),
+            })
+        };
+
+        // components of composite glyphs are kept as well
+        let mut kept = glyphs.clone();
+        kept.insert(0);
+        let mut pending = kept.iter().copied().collect::<Vec<u16>>();
+        while let Some(glyph) = pending.pop() {
+            let (start, end) = location(glyph as usize)?;
+            if end <= start || end > glyf.len() || (read_u16(glyf, start)? as i16) >= 0 {
+                continue;
+            }
+            let mut offset = start + 10;
+            loop {
+                let flags = read_u16(glyf, offset)?;
+                let component = read_u16(glyf, offset + 2)?;
+                if kept.insert(component) {
+                    pending.push(component);
+                }
+                offset += 4 + if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                    4
+                } else {
+                    2
+                };
+                offset += match flags {
+                    f if f & WE_HAVE_A_SCALE != 0 => 2,
+                    f if f & WE_HAVE_AN_X_AND_Y_SCALE != 0 => 4,
+                    f if f & WE_HAVE_A_TWO_BY_TWO != 0 => 8,
+                    _ => 0,
+                };
+                if flags & MORE_COMPONENTS == 0 {
+                    break;
+                }
+            }
+        }
+
+        let count = self.widths.len();
+        let mut new_glyf = vec![];
+        let mut new_loca = Vec::with_capacity((count + 1) * 4);
+        for glyph in 0..count {
+            new_loca.extend((new_glyf.len() as u32).to_be_bytes());
+            if kept.contains(&(glyph as u16)) {
+                let (start, end) = location(glyph)?;
+                if start < end && end <= glyf.len() {
+                    new_glyf.extend(&glyf[start..end]);
+                    new_glyf.resize(new_glyf.len().next_multiple_of(4), 0);
+                }
+            }
+        }
+        new_loca.extend((new_glyf.len() as u32).to_be_bytes());
+        let mut new_head = head.to_vec();
+        // checksum adjustment is recalculated, loca is written in long format
+        new_head[8..12].copy_from_slice(&[0; 4]);
+        new_head[50..52].copy_from_slice(&1u16.to_be_bytes());
+
+        let mut tables = vec![];
+        for tag in SUBSET_TABLES {
+            let data = match tag {
+                b"glyf" => new_glyf.clone(),
+                b"loca" => new_loca.clone(),
+                b"head" => new_head.clone(),
+                _ => match self.table(tag) {
+                    Ok(data) => data.to_vec(),
+                    Err(_) => continue,
+                },
+            };
+            tables.push((*tag, data));
+        }
+        Ok(write_font(&tables))
+    }
+}
+
+/// sfnt with tables sorted by tag
+fn write_font(tables: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+    let count = tables.len() as u16;
+    let selector = 15 - count.leading_zeros() as u16;
+    let search_range = (1u16 << selector) * 16;
+    let mut font = vec![];
+    font.extend(0x0001_0000u32.to_be_bytes());
+    font.extend(count.to_be_bytes());
+    font.extend(search_range.to_be_bytes());
+    font.extend(selector.to_be_bytes());
+    font.extend((count * 16 - search_range).to_be_bytes());
+    let mut offset = 12 + tables.len() * 16;
+    let mut head_offset = None;
+    for (tag, data) in tables {
+        if tag == b"head" {
+            head_offset = Some(offset);
+        }
+        font.extend(tag);
+        font.extend(checksum(data).to_be_bytes());
+        font.extend((offset as u32).to_be_bytes());
+        font.extend((data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    for (_, data) in tables {
+        font.extend(data);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+    if let Some(head) = head_offset {
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&font));
+        font[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    font
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4)
+        .map(|chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u32::from_be_bytes(word)
+        })
+        .fold(0, u32::wrapping_add)
+}
+
+/// character to glyph mapping from unicode subtables, format 12 for full range and format 4 for BMP
+fn parse_cmap(cmap: &[u8]) -> anyhow::Result<HashMap<u32, u16>> {
+    let count = read_u16(cmap, 2)? as usize;
+    let mut subtables = vec![];
+    for index in 0..count {
+        let record = 4 + index * 8;
+        let platform = read_u16(cmap, record)?;
+        let encoding = read_u16(cmap, record + 2)?;
+        let offset = read_u32(cmap, record + 4)? as usize;
+        let format = read_u16(cmap, offset)?;
+        let unicode = platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10));
+        if unicode && (format == 4 || format == 12) {
+            subtables.push((format, offset));
+        }
+    }
+    // prefer the full range subtable
+    subtables.sort_by_key(|(format, _)| std::cmp::Reverse(*format));
+    let (format, offset) = *subtables
+        .first()
+        .ok_or(anyhow!("font has no unicode cmap"))?;
+    let mut map = HashMap::new();
+    if format == 12 {
+        let groups = read_u32(cmap, offset + 12)? as usize;
+        for group in 0..groups {
+            let record = offset + 16 + group * 12;
+            let start = read_u32(cmap, record)?;
+            let end = read_u32(cmap, record + 4)?;
+            let glyph = read_u32(cmap, record + 8)?;
+            for code in start..=end {
+                map.insert(code, (glyph + code - start) as u16);
+            }
+        }
+        return Ok(map);
+    }
+    let segments = read_u16(cmap, offset + 6)? as usize / 2;
+    let ends = offset + 14;
+    let starts = ends + segments * 2 + 2;
+    let deltas = starts + segments * 2;
+    let range_offsets = deltas + segments * 2;
+    for segment in 0..segments {
+        let end = read_u16(cmap, ends + segment * 2)? as u32;
+        let start = read_u16(cmap, starts + segment * 2)? as u32;
+        let delta = read_u16(cmap, deltas + segment * 2)?;
+        let range_offset = read_u16(cmap, range_offsets + segment * 2)? as usize;
+        for code in start..=end.min(0xFFFE) {
+            let glyph = match range_offset {
+                0 => (code as u16).wrapping_add(delta),
+                _ => {
+                    let address =
+                        range_offsets + segment * 2 + range_offset + (code - start) as usize * 2;
+                    match read_u16(cmap, address)? {
+                        0 => 0,
+                        glyph => glyph.wrapping_add(delta),
+                    }
+                }
+            };
+            if glyph != 0 {
+                map.insert(code, glyph);
+            }
+        }
+    }
+    Ok(map)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> anyhow::Result<u16> {
+    let bytes = data
+        .get(offset..offset + 2)
+        .ok_or(anyhow!("unexpected end of font"))?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> anyhow::Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or(anyhow!("unexpected end of font"))?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// glyph 0 is empty, glyphs 1 and 2 are simple, glyph 3 is composed of glyphs 1 and 2,
+    /// also used by combine tests as font of native toc
+    pub(crate) fn test_font() -> Vec<u8> {
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        head[40..42].copy_from_slice(&900u16.to_be_bytes());
+        let mut hhea = vec![0; 36];
+        hhea[4..6].copy_from_slice(&800u16.to_be_bytes());
+        hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+        hhea[34..36].copy_from_slice(&3u16.to_be_bytes());
+        let mut maxp = vec![0; 6];
+        maxp[4..6].copy_from_slice(&4u16.to_be_bytes());
+        let hmtx = [500u16, 0, 600, 0, 700, 0]
+            .iter()
+            .flat_map(|n| n.to_be_bytes())
+            .collect::<Vec<u8>>();
+        // format 4 subtable mapping "A" to "C" onto glyphs 1 to 3
+        let cmap = [
+            0u16,
+            1,
+            3,
+            1,
+            0,
+            12,
+            4,
+            32,
+            0,
+            4,
+            4,
+            1,
+            0,
+            0x43,
+            0xFFFF,
+            0,
+            0x41,
+            0xFFFF,
+            1u16.wrapping_sub(0x41),
+            1,
+            0,
+            0,
+        ]
+        .iter()
+        .flat_map(|n| n.to_be_bytes())
+        .collect::<Vec<u8>>();
+        let simple = [&1u16.to_be_bytes()[..], &[0; 10]].concat();
+        let mut composite = (-1i16).to_be_bytes().to_vec();
+        composite.extend([0; 8]);
+        for (flags, glyph, args) in [
+            (ARG_1_AND_2_ARE_WORDS | MORE_COMPONENTS, 1u16, 4),
+            (0, 2, 2),
+        ] {
+            composite.extend(flags.to_be_bytes());
+            composite.extend(glyph.to_be_bytes());
+            composite.extend(vec![0; args]);
+        }
+        let glyf = [simple.clone(), simple, composite].concat();
+        let loca = [0u16, 0, 6, 12, 24]
+            .iter()
+            .flat_map(|n| n.to_be_bytes())
+            .collect::<Vec<u8>>();
+        write_font(&[
+            (*b"cmap", cmap),
+            (*b"glyf", glyf),
+            (*b"head", head),
+            (*b"hhea", hhea),
+            (*b"hmtx", hmtx),
+            (*b"loca", loca),
+            (*b"maxp", maxp),
+        ])
+    }
+
+    /// lengths of glyphs in a font with long loca
+    fn glyph_lengths(font: &[u8]) -> Vec<usize> {
+        let count = read_u16(font, 4).unwrap() as usize;
+        let loca = (0..count)
+            .map(|index| 12 + index * 16)
+            .find(|record| &font[*record..*record + 4] == b"loca")
+            .map(|record| read_u32(font, record + 8).unwrap() as usize)
+            .unwrap();
+        let offsets = (0..=4)
+            .map(|glyph| read_u32(font, loca + glyph * 4).unwrap() as usize)
+            .collect::<Vec<usize>>();
+        offsets.windows(2).map(|w| w[1] - w[0]).collect()
+    }
+
+    #[test]
+    fn parse_test() -> anyhow::Result<()> {
+        let font = TrueTypeFont::parse(test_font(), "Test Sans")?;
+        assert_eq!(font.name, "TestSans");
+        assert_eq!(
+            (font.units_per_em, font.ascent, font.descent),
+            (1000, 800, -200)
+        );
+        assert_eq!(font.bbox, [0, 0, 900, 0]);
+        assert_eq!(font.glyph('B'), 2);
+        assert_eq!(font.glyph('Z'), 0);
+        // glyph 3 is after the last metric and shares its width
+        assert_eq!(font.advance(3), 700.0);
+        assert_eq!(font.width("AB", 10.0), 13.0);
+        assert!(TrueTypeFont::parse(vec![0; 8], "Broken").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn subset_test() -> anyhow::Result<()> {
+        let font = TrueTypeFont::parse(test_font(), "Test")?;
+        // components of composite glyph are followed
+        let subset = font.subset(&BTreeSet::from([3]))?;
+        assert_eq!(glyph_lengths(&subset), vec![0, 12, 12, 24]);
+        assert_eq!(checksum(&subset), 0xB1B0_AFBA);
+        let subset = font.subset(&BTreeSet::from([2]))?;
+        assert_eq!(glyph_lengths(&subset), vec![0, 0, 12, 0]);
+        // glyph ids and widths are kept
+        let count = read_u16(&subset, 4)? as usize;
+        let tags = (0..count)
+            .map(|index| &subset[12 + index * 16..16 + index * 16])
+            .collect::<Vec<&[u8]>>();
+        assert!(tags.contains(&b"hmtx".as_slice()) && !tags.contains(&b"cmap".as_slice()));
+        Ok(())
+    }
+}
@@ -0,0 +1,534 @@
+use super::font::TrueTypeFont;
+use crate::combiner::pdf::location::Location;
+use lopdf::{
+    content::{Content, Operation},
+    dictionary, Dictionary, Document, Object, ObjectId, Stream, StringFormat,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+const MARGIN: f32 = 36.0;
+const HEADER_SIZE: f32 = 10.0;
+const TITLE_SIZE: f32 = 16.0;
+const ITEM_SIZE: f32 = 10.5;
+/// line height relative to font size
+const LINE_SPACING: f32 = 1.4;
+const ITEM_GAP: f32 = 4.0;
+/// space around dot leaders
+const LEADER_GAP: f32 = 6.0;
+const ATTACHMENT_MARK: &str = "[RTF]";
+const FONT_NAME: &str = "F1";
+/// width of stroke simulating bold text
+const BOLD_STROKE: f32 = 0.3;
+
+/// text placed on a toc page, `y` is the baseline
+#[derive(Debug, Clone, PartialEq)]
+struct Text {
+    x: f32,
+    y: f32,
+    size: f32,
+    text: String,
+    bold: bool,
+    gray: bool,
+}
+
+/// link area on a toc page and the output it points at
+#[derive(Debug, Clone, PartialEq)]
+pub struct TocLink {
+    /// index of toc page
+    pub page: usize,
+    pub rect: [f32; 4],
+    /// id of output
+    pub output: usize,
+}
+
+#[derive(Debug, Default)]
+struct Page {
+    texts: Vec<Text>,
+    /// rect and output id of links
+    links: Vec<([f32; 4], usize)>,
+}
+
+/// lay out toc straight into pdf with the TrueType font at `font`, long titles wrap, dot leaders run to
+/// right aligned page numbers, and a page breaks when it is full. return the number of pages and the links,
+/// which are added by `link` once pages of outputs are known in the combined pdf
+pub fn print(
+    items: &[Location],
+    size: (f32, f32),
+    title: &str,
+    headers: &(String, String, String, String),
+    indent: f32,
+    font: &Path,
+    dest: &Path,
+) -> anyhow::Result<(usize, Vec<TocLink>)> {
+    let font = TrueTypeFont::load(font)
+        .map_err(|err| anyhow::anyhow!("failed to load toc font {}: {}", font.display(), err))?;
+    let pages = layout(items, size, title, headers, indent, &|text, size| {
+        font.width(text, size)
+    });
+    write(&pages, size, &font, dest)?;
+    let links = pages
+        .iter()
+        .enumerate()
+        .flat_map(|(page, content)| {
+            content.links.iter().map(move |(rect, output)| TocLink {
+                page,
+                rect: *rect,
+                output: *output,
+            })
+        })
+        .collect();
+    Ok((pages.len(), links))
+}
+
+/// add link annotations to `toc_pages`, `target` gives the entries pointing an annotation at the output,
+/// such as `Dest` with the page of output, links without target are skipped
+pub fn link(
+    doc: &mut Document,
+    toc_pages: &[ObjectId],
+    links: &[TocLink],
+    target: impl Fn(usize) -> Option<Dictionary>,
+) -> anyhow::Result<()> {
+    for link in links {
+        let (Some(page), Some(target)) = (toc_pages.get(link.page), target(link.output)) else {
+            continue;
+        };
+        let mut annotation = dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Link",
+            "Rect" => link.rect.iter().map(|n| (*n).into()).collect::<Vec<Object>>(),
+            "Border" => vec![0.into(), 0.into(), 0.into()],
+        };
+        annotation.extend(&target);
+        let annotation = doc.add_object(annotation);
+        let page = doc.get_dictionary_mut(*page)?;
+        match page.get_mut(b"Annots") {
+            Ok(Object::Array(annotations)) => annotations.push(annotation.into()),
+            _ => page.set("Annots", vec![annotation.into()]),
+        }
+    }
+    Ok(())
+}
+
+fn layout(
+    items: &[Location],
+    (width, height): (f32, f32),
+    title: &str,
+    headers: &(String, String, String, String),
+    indent: f32,
+    measure: &dyn Fn(&str, f32) -> f32,
+) -> Vec<Page> {
+    let items = items
+        .iter()
+        .filter(|item| !item.title.is_empty())
+        .collect::<Vec<&Location>>();
+    let line_height = ITEM_SIZE * LINE_SPACING;
+    // page numbers and ranges share a column wide enough for all of them
+    let number_width = items
+        .iter()
+        .map(|item| measure(&item.page_number(), ITEM_SIZE))
+        .fold(measure("0000", ITEM_SIZE), f32::max);
+    let number_right = width - MARGIN;
+    let dot_width = measure(".", ITEM_SIZE).max(1.0);
+
+    let mut pages = vec![];
+    let mut page = Page::default();
+    let mut y = header(&mut page, (width, height), title, headers, measure);
+    for item in items {
+        let left = MARGIN + item.level as f32 * indent;
+        let available = number_right - number_width - LEADER_GAP * 2.0 - left;
+        let lines = wrap(&item.title, available, &|text| measure(text, ITEM_SIZE));
+        let item_height = lines.len() as f32 * line_height + ITEM_GAP;
+        if y - item_height < MARGIN && !page.texts.is_empty() {
+            pages.push(page);
+            page = Page::default();
+            y = header(&mut page, (width, height), "", headers, measure);
+        }
+        let top = y;
+        let mut end = left;
+        for line in &lines {
+            y -= line_height;
+            end = left + measure(line, ITEM_SIZE);
+            page.texts.push(Text {
+                x: left,
+                y,
+                size: ITEM_SIZE,
+                text: line.clone(),
+                bold: item.divider || item.heading,
+                gray: false,
+            });
+        }
+        if item.attachment {
+            page.texts.push(Text {
+                x: end + LEADER_GAP,
+                y,
+                size: ITEM_SIZE,
+                text: ATTACHMENT_MARK.into(),
+                bold: false,
+                gray: true,
+            });
+            end += LEADER_GAP + measure(ATTACHMENT_MARK, ITEM_SIZE);
+        }
+        let number = item.page_number();
+        let number_x = number_right - measure(&number, ITEM_SIZE);
+        // dots are aligned to the page number column
+        let leader_end = number_right - number_width - LEADER_GAP;
+        let dots = ((leader_end - end - LEADER_GAP) / dot_width)
+            .floor()
+            .max(0.0) as usize;
+        if dots > 0 {
+            page.texts.push(Text {
+                x: leader_end - dots as f32 * dot_width,
+                y,
+                size: ITEM_SIZE,
+                text: ".".repeat(dots),
+                bold: false,
+                gray: false,
+            });
+        }
+        page.texts.push(Text {
+            x: number_x,
+            y,
+            size: ITEM_SIZE,
+            text: number,
+            bold: false,
+            gray: false,
+        });
+        y -= ITEM_GAP;
+        if let Some(id) = item.id {
+            page.links.push(([MARGIN, y, number_right, top], id));
+        }
+    }
+    pages.push(page);
+    pages
+}
+
+/// headers on every page and the title on the first page, return the baseline where items start
+fn header(
+    page: &mut Page,
+    (width, height): (f32, f32),
+    title: &str,
+    headers: &(String, String, String, String),
+    measure: &dyn Fn(&str, f32) -> f32,
+) -> f32 {
+    let mut y = height - MARGIN;
+    for (left, right) in [(&headers.0, &headers.1), (&headers.2, &headers.3)] {
+        y -= HEADER_SIZE * LINE_SPACING;
+        for (text, x) in [
+            (left, MARGIN),
+            (right, width - MARGIN - measure(right, HEADER_SIZE)),
+        ] {
+            if !text.is_empty() {
+                page.texts.push(Text {
+                    x,
+                    y,
+                    size: HEADER_SIZE,
+                    text: text.clone(),
+                    bold: false,
+                    gray: false,
+                });
+            }
+        }
+    }
+    if !title.is_empty() {
+        y -= TITLE_SIZE * 2.0;
+        page.texts.push(Text {
+            x: (width - measure(title, TITLE_SIZE)) / 2.0,
+            y,
+            size: TITLE_SIZE,
+            text: title.into(),
+            bold: true,
+            gray: false,
+        });
+    }
+    y - ITEM_GAP * 2.0
+}
+
+/// break text into lines no wider than `width`, at spaces when possible, otherwise at any character
+/// such as in cjk titles
+fn wrap(text: &str, width: f32, measure: &dyn Fn(&str) -> f32) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for character in text.chars() {
+        line.push(character);
+        if measure(&line) <= width {
+            continue;
+        }
+        line.pop();
+        let rest = match line.rfind(' ') {
+            Some(index) if index > 0 => {
+                let rest = line[index + 1..].to_string();
+                line.truncate(index);
+                rest
+            }
+            _ => String::new(),
+        };
+        if !line.is_empty() {
+            lines.push(line);
+        }
+        line = rest;
+        if !(line.is_empty() && character == ' ') {
+            line.push(character);
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn write(pages: &[Page], size: (f32, f32), font: &TrueTypeFont, dest: &Path) -> anyhow::Result<()> {
+    let mut doc = Document::with_version("1.5");
+    let mut unicode = BTreeMap::new();
+    for text in pages.iter().flat_map(|page| page.texts.iter()) {
+        for character in text.text.chars() {
+            unicode.entry(font.glyph(character)).or_insert(character);
+        }
+    }
+    let glyphs = unicode.keys().copied().collect::<BTreeSet<u16>>();
+    let font_id = embed_font(&mut doc, font, &glyphs, &unicode)?;
+
+    let pages_id = doc.new_object_id();
+    let mut kids = vec![];
+    for page in pages {
+        let mut operations = vec![];
+        for text in &page.texts {
+            let encoded = text
+                .text
+                .chars()
+                .flat_map(|c| font.glyph(c).to_be_bytes())
+                .collect::<Vec<u8>>();
+            operations.push(Operation::new("q", vec![]));
+            if text.gray {
+                operations.push(Operation::new("g", vec![0.5.into()]));
+            }
+            if text.bold {
+                operations.push(Operation::new("w", vec![BOLD_STROKE.into()]));
+                operations.push(Operation::new("Tr", vec![2.into()]));
+            }
+            operations.extend([
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec![FONT_NAME.into(), text.size.into()]),
+                Operation::new("Td", vec![text.x.into(), text.y.into()]),
+                Operation::new(
+                    "Tj",
+                    vec![Object::String(encoded, StringFormat::Hexadecimal)],
+                ),
+                Operation::new("ET", vec![]),
+                Operation::new("Q", vec![]),
+            ]);
+        }
+        let content = doc.add_object(Stream::new(
+            dictionary! {},
+            Content { operations }.encode()?,
+        ));
+        kids.push(
+            doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => vec![0.into(), 0.into(), size.0.into(), size.1.into()],
+                "Resources" => dictionary! { "Font" => dictionary! { FONT_NAME => font_id } },
+                "Contents" => content,
+            })
+            .into(),
+        );
+    }
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => kids, "Count" => count }),
+    );
+    let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog);
+    doc.compress();
+    doc.save(dest)?;
+    Ok(())
+}
+
+/// Type0 font with subset of TrueType font, glyph ids are used as cids
+fn embed_font(
+    doc: &mut Document,
+    font: &TrueTypeFont,
+    glyphs: &BTreeSet<u16>,
+    unicode: &BTreeMap<u16, char>,
+) -> anyhow::Result<lopdf::ObjectId> {
+    let program = font.subset(glyphs)?;
+    let length = program.len() as i64;
+    let mut stream = Stream::new(dictionary! { "Length1" => length }, program);
+    stream.compress()?;
+    let program = doc.add_object(stream);
+
+    // subset fonts are named with a tag of six uppercase letters
+    let hash = Sha256::digest(
+        glyphs
+            .iter()
+            .flat_map(|g| g.to_be_bytes())
+            .collect::<Vec<u8>>(),
+    );
+    let tag = hash[0..6]
+        .iter()
+        .map(|b| (b'A' + b % 26) as char)
+        .collect::<String>();
+    let name = format!("{}+{}", tag, font.name);
+    let scale = |value: i16| (value as f32 * 1000.0 / font.units_per_em as f32) as i64;
+    let descriptor = doc.add_object(dictionary! {
+        "Type" => "FontDescriptor",
+        "FontName" => name.as_str(),
+        "Flags" => 4,
+        "FontBBox" => font.bbox.iter().map(|v| scale(*v).into()).collect::<Vec<Object>>(),
+        "ItalicAngle" => 0,
+        "Ascent" => scale(font.ascent),
+        "Descent" => scale(font.descent),
+        "CapHeight" => scale(font.ascent),
+        "StemV" => 80,
+        "FontFile2" => program,
+    });
+    let widths = glyphs
+        .iter()
+        .flat_map(|glyph| {
+            [
+                Object::Integer(*glyph as i64),
+                vec![Object::Integer(font.advance(*glyph).round() as i64)].into(),
+            ]
+        })
+        .collect::<Vec<Object>>();
+    let descendant = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "CIDFontType2",
+        "BaseFont" => name.as_str(),
+        "CIDSystemInfo" => dictionary! {
+            "Registry" => Object::string_literal("Adobe"),
+            "Ordering" => Object::string_literal("Identity"),
+            "Supplement" => 0,
+        },
+        "FontDescriptor" => descriptor,
+        "W" => widths,
+        "CIDToGIDMap" => "Identity",
+    });
+    let to_unicode = doc.add_object(Stream::new(
+        dictionary! {},
+        to_unicode(unicode).into_bytes(),
+    ));
+    Ok(doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type0",
+        "BaseFont" => name.as_str(),
+        "Encoding" => "Identity-H",
+        "DescendantFonts" => vec![descendant.into()],
+        "ToUnicode" => to_unicode,
+    }))
+}
+
+/// CMap from glyph ids to unicode, so that text of toc could be extracted and searched
+fn to_unicode(unicode: &BTreeMap<u16, char>) -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let entries = unicode.iter().collect::<Vec<(&u16, &char)>>();
+    // at most 100 entries in a bfchar block
+    for chunk in entries.chunks(100) {
+        cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+        for (glyph, character) in chunk {
+            let code = character
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .map(|unit| format!("{:04X}", unit))
+                .collect::<String>();
+            cmap.push_str(&format!("<{:04X}> <{}>\n", glyph, code));
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    #[test]
+    fn wrap_test() {
+        let measure = |text: &str| text.chars().count() as f32;
+        assert_eq!(
+            wrap("Table 14.1.1: Demographic and Baseline", 16.0, &measure),
+            vec!["Table 14.1.1:", "Demographic and", "Baseline"]
+        );
+        assert_eq!(
+            wrap("表14.1.1人口学资料", 6.0, &measure),
+            vec!["表14.1.", "1人口学资料"]
+        );
+        assert_eq!(wrap("", 6.0, &measure), vec![""]);
+    }
+
+    #[test]
+    fn layout_test() {
+        let items = (0..60)
+            .map(|id| Location {
+                id: Some(id),
+                title: format!(
+                    "Table 14.1.{}: Demographic and Baseline Characteristics",
+                    id
+                ),
+                page: id * 2,
+                path: PathBuf::new(),
+                divider: false,
+                attachment: id == 0,
+                ..Default::default()
+            })
+            .collect::<Vec<Location>>();
+        let measure = |text: &str, size: f32| text.chars().count() as f32 * size * 0.5;
+        let headers = ("Study".into(), "Confidential".into(), "".into(), "".into());
+        let pages = layout(
+            &items,
+            (842.0, 595.0),
+            "Table of Content",
+            &headers,
+            12.0,
+            &measure,
+        );
+        assert!(pages.len() > 1);
+        let links = pages.iter().map(|p| p.links.len()).sum::<usize>();
+        assert_eq!(links, 60);
+        // every page repeats headers, only the first page has title
+        assert!(pages[1].texts.iter().any(|t| t.text == "Confidential"));
+        assert!(!pages[1].texts.iter().any(|t| t.text == "Table of Content"));
+        assert!(pages[0].texts.iter().any(|t| t.text == ATTACHMENT_MARK));
+        for page in &pages {
+            assert!(page.links.iter().all(|(rect, _)| rect[1] >= MARGIN));
+        }
+    }
+
+    #[test]
+    fn link_test() -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.5");
+        let pages = (0..3)
+            .map(|_| doc.add_object(dictionary! { "Type" => "Page" }))
+            .collect::<Vec<ObjectId>>();
+        let links = [
+            TocLink {
+                page: 0,
+                rect: [36.0, 700.0, 559.0, 714.0],
+                output: 7,
+            },
+            TocLink {
+                page: 0,
+                rect: [36.0, 680.0, 559.0, 694.0],
+                output: 8,
+            },
+        ];
+        link(&mut doc, &pages[0..1], &links, |output| {
+            (output == 7).then(|| dictionary! { "Dest" => vec![pages[2].into(), "XYZ".into()] })
+        })?;
+        let annotations = doc.get_page_annotations(pages[0])?;
+        assert_eq!(annotations.len(), 1);
+        let dest = annotations[0].get(b"Dest")?.as_array()?;
+        assert_eq!(dest[0].as_reference()?, pages[2]);
+        Ok(())
+    }
+}
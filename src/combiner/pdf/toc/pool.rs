@@ -0,0 +1,159 @@
+use crate::platform::browser;
+use headless_chrome::{types::PrintToPdfOptions, Browser};
+use std::{
+    fmt,
+    sync::{mpsc::Sender, Arc, Condvar, Mutex},
+    time::Instant,
+};
+
+/// one headless browser shared by all renders of a fusion run, at most `max_tabs` pages are printed at
+/// the same time, the browser is launched on first use and relaunched when it crashed
+pub struct BrowserPool {
+    browser: Mutex<Option<Browser>>,
+    /// tabs in use
+    tabs: Mutex<usize>,
+    released: Condvar,
+    max_tabs: usize,
+    logger: Arc<Mutex<Sender<String>>>,
+}
+
+/// tab slot, released when dropped
+struct Permit<'a>(&'a BrowserPool);
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self.0.tabs.lock().unwrap() -= 1;
+        self.0.released.notify_one();
+    }
+}
+
+impl fmt::Debug for BrowserPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BrowserPool")
+            .field("max_tabs", &self.max_tabs)
+            .finish()
+    }
+}
+
+impl BrowserPool {
+    pub fn new(max_tabs: usize, logger: Arc<Mutex<Sender<String>>>) -> Self {
+        BrowserPool {
+            browser: Mutex::new(None),
+            tabs: Mutex::new(0),
+            released: Condvar::new(),
+            max_tabs: max_tabs.max(1),
+            logger,
+        }
+    }
+
+    /// print page at `url` into pdf, `name` is used in logs only
+    pub fn print(
+        &self,
+        url: &str,
+        name: &str,
+        options: impl Fn() -> PrintToPdfOptions,
+    ) -> anyhow::Result<Vec<u8>> {
+        let start = Instant::now();
+        let _permit = self.acquire();
+        let waited = start.elapsed();
+        let pdf = match self.print_once(url, options()) {
+            Ok(pdf) => pdf,
+            // a crashed browser is relaunched and the page is printed again, other errors are returned
+            Err(err) if self.relaunch() => {
+                self.log(format!(
+                    "[WARN] browser crashed while printing {}, relaunched: {}",
+                    name, err
+                ));
+                self.print_once(url, options())?
+            }
+            Err(err) => return Err(err),
+        };
+        self.log(format!(
+            "[INFO] {} printed in {:.1}s, waited {:.1}s for tab",
+            name,
+            start.elapsed().as_secs_f32(),
+            waited.as_secs_f32()
+        ));
+        Ok(pdf)
+    }
+
+    fn acquire(&self) -> Permit<'_> {
+        let mut tabs = self.tabs.lock().unwrap();
+        while *tabs >= self.max_tabs {
+            tabs = self.released.wait(tabs).unwrap();
+        }
+        *tabs += 1;
+        Permit(self)
+    }
+
+    fn print_once(&self, url: &str, options: PrintToPdfOptions) -> anyhow::Result<Vec<u8>> {
+        let browser = self.browser()?;
+        let tab = browser.new_tab()?;
+        let pdf = tab
+            .navigate_to(url)
+            .and_then(|tab| tab.wait_until_navigated())
+            .and_then(|tab| tab.print_to_pdf(Some(options)));
+        tab.close(true).ok();
+        pdf
+    }
+
+    /// running browser, launched if there is none
+    fn browser(&self) -> anyhow::Result<Browser> {
+        let mut current = self.browser.lock().unwrap();
+        if let Some(browser) = current.as_ref() {
+            return Ok(browser.clone());
+        }
+        let browser = browser()?;
+        self.log(format!(
+            "[INFO] browser launched, process {}",
+            browser.get_process_id().unwrap_or_default()
+        ));
+        *current = Some(browser.clone());
+        Ok(browser)
+    }
+
+    /// drop the browser if it does not respond, return true if it should be launched again
+    fn relaunch(&self) -> bool {
+        let mut current = self.browser.lock().unwrap();
+        match current.as_ref() {
+            Some(browser) if browser.get_version().is_ok() => false,
+            _ => {
+                current.take();
+                true
+            }
+        }
+    }
+
+    fn log(&self, message: String) {
+        self.logger
+            .lock()
+            .unwrap()
+            .send(format!("{}\n", message))
+            .ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, thread, time::Duration};
+    #[test]
+    fn permit_test() {
+        let (tx, _rx) = mpsc::channel();
+        let pool = Arc::new(BrowserPool::new(2, Arc::new(Mutex::new(tx))));
+        let first = pool.acquire();
+        let _second = pool.acquire();
+        let waiting = {
+            let pool = Arc::clone(&pool);
+            thread::spawn(move || {
+                let _third = pool.acquire();
+                let tabs = *pool.tabs.lock().unwrap();
+                tabs
+            })
+        };
+        thread::sleep(Duration::from_millis(100));
+        assert!(!waiting.is_finished());
+        drop(first);
+        assert_eq!(waiting.join().unwrap(), 2);
+    }
+}
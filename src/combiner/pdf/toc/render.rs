@@ -1,19 +1,66 @@
-use super::template;
-use crate::combiner::pdf::location::Location;
-use anyhow::Ok;
-use headless_chrome::{types::PrintToPdfOptions, Browser};
+use super::{
+    native::{self, TocLink},
+    pool::BrowserPool,
+    template,
+};
+use crate::{
+    combiner::pdf::{location::Location, metadata::Timestamp},
+    config::{
+        pdf::{CoverSetting, Divider, PageOrientation, PaperSize, RenderBackend},
+        utils::Language,
+    },
+    platform::{browser, file_url},
+};
+use anyhow::{anyhow, Ok};
+use headless_chrome::types::PrintToPdfOptions;
+use lopdf::Document;
 use serde::Serialize;
-use std::{fs, path::Path};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tera::{Context, Tera};
 
 const TOC_TEMPLATE: &str = "toc";
+const COVER_TEMPLATE: &str = "cover";
+const DIVIDER_TEMPLATE: &str = "divider";
 
 #[derive(Debug, Serialize)]
 struct RenderData {
     pub items: Vec<Location>,
     pub content: String,
     pub size: ValidSize,
+    pub orientation: String,
+    /// size of paper in points, pages of toc are laid out in it
+    pub page_width: f32,
+    pub page_height: f32,
     pub toc_headers: (String, String, String, String),
+    pub study: String,
+    pub company: String,
+    pub purpose: String,
+    pub language: Language,
+    pub date: String,
+    pub total_pages: usize,
+    /// indentation of every level in points
+    pub indent: f32,
+}
+
+#[derive(Debug, Serialize)]
+struct CoverData {
+    pub cover: CoverSetting,
+    pub date: String,
+    /// labels of protocol, version and date
+    pub labels: (String, String, String),
+    pub size: ValidSize,
+    pub orientation: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DividerData {
+    pub divider: Divider,
+    pub size: ValidSize,
+    pub orientation: String,
 }
 
 #[derive(Debug, Default, Clone, Serialize)]
@@ -23,6 +70,15 @@ pub enum ValidSize {
     LETTER,
 }
 
+impl From<&PaperSize> for ValidSize {
+    fn from(size: &PaperSize) -> Self {
+        match size {
+            PaperSize::A4 => ValidSize::A4,
+            PaperSize::Letter => ValidSize::LETTER,
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Render {
     template: Tera,
@@ -32,15 +88,26 @@ pub struct Render {
     purpose: String,
     toc_headers: (String, String, String, String),
     pub size: ValidSize,
+    orientation: PageOrientation,
+    backend: RenderBackend,
+    language: Language,
+    date: String,
+    total_pages: usize,
+    indent: f32,
+    browsers: Option<Arc<BrowserPool>>,
+    font: Option<PathBuf>,
 }
 
 impl Render {
     pub fn new() -> anyhow::Result<Render> {
         let mut tmpl = Tera::default();
         tmpl.add_raw_template(TOC_TEMPLATE, template::TEMPLATE)?;
+        tmpl.add_raw_template(COVER_TEMPLATE, template::COVER_TEMPLATE)?;
+        tmpl.add_raw_template(DIVIDER_TEMPLATE, template::DIVIDER_TEMPLATE)?;
         tmpl.autoescape_on(vec![]);
         Ok(Render {
             template: tmpl,
+            date: Timestamp::now().date(),
             ..Default::default()
         })
     }
@@ -73,12 +140,78 @@ impl Render {
         self
     }
 
+    pub fn set_orientation(&mut self, orientation: &PageOrientation) -> &mut Self {
+        self.orientation = orientation.clone();
+        self
+    }
+
+    pub fn set_backend(&mut self, backend: &RenderBackend) -> &mut Self {
+        self.backend = backend.clone();
+        self
+    }
+
+    pub fn set_language(&mut self, language: &Language) -> &mut Self {
+        self.language = language.clone();
+        self
+    }
+
+    /// pages of outputs listed in toc
+    pub fn set_total_pages(&mut self, total_pages: usize) -> &mut Self {
+        self.total_pages = total_pages;
+        self
+    }
+
+    /// indentation of every level of multi-level toc in points
+    pub fn set_indent(&mut self, indent: f32) -> &mut Self {
+        self.indent = indent;
+        self
+    }
+
+    /// replace the built-in toc template with a tera template file
+    pub fn set_template(&mut self, path: &Path) -> anyhow::Result<&mut Self> {
+        self.template.add_template_file(path, Some(TOC_TEMPLATE))?;
+        Ok(self)
+    }
+
+    /// print html with the shared browser pool instead of launching a browser for each page
+    pub fn set_browsers(&mut self, browsers: &Option<Arc<BrowserPool>>) -> &mut Self {
+        self.browsers = browsers.clone();
+        self
+    }
+    /// TrueType font of native backend
+    pub fn set_font(&mut self, font: &Option<PathBuf>) -> &mut Self {
+        self.font = font.clone();
+        self
+    }
+
     pub fn set_toc_headers(&mut self, toc_headers: &(String, String, String, String)) -> &mut Self {
         self.toc_headers = toc_headers.clone();
         self
     }
 
-    pub fn print(&self, items: &[Location], dest: &Path) -> anyhow::Result<()> {
+    /// print toc into `dest`, return the number of pages printed and links of native backend,
+    /// links printed by the browser are named destinations of output ids instead
+    pub fn print(&self, items: &[Location], dest: &Path) -> anyhow::Result<(usize, Vec<TocLink>)> {
+        if self.backend == RenderBackend::Native {
+            let font = self.font.as_ref().ok_or(anyhow!(
+                "native toc backend requires a TrueType font, set `font` of toc template"
+            ))?;
+            return native::print(
+                items,
+                self.paper(),
+                &self.content,
+                &self.toc_headers,
+                self.indent,
+                font,
+                dest,
+            );
+        }
+        self.print_page(&self.html(items)?, dest)?;
+        Ok((Document::load(dest)?.get_pages().len(), vec![]))
+    }
+
+    /// html of toc rendered from the template
+    fn html(&self, items: &[Location]) -> anyhow::Result<String> {
         let data = RenderData {
             content: self.content.clone(),
             items: items
@@ -87,28 +220,104 @@ impl Render {
                 .filter(|item| !item.title.is_empty())
                 .collect::<Vec<Location>>(),
             size: self.size.clone(),
+            orientation: self.orientation.css().into(),
+            page_width: self.paper().0,
+            page_height: self.paper().1,
             toc_headers: self.toc_headers.clone(),
+            study: self.study.clone(),
+            company: self.company.clone(),
+            purpose: self.purpose.clone(),
+            language: self.language.clone(),
+            date: self.date.clone(),
+            total_pages: self.total_pages,
+            indent: self.indent,
         };
-        let bytes = self
+        self.render(TOC_TEMPLATE, &data)
+    }
+
+    /// width and height of paper in points
+    fn paper(&self) -> (f32, f32) {
+        let (width, height) = match self.size {
+            ValidSize::A4 => PaperSize::A4,
+            ValidSize::LETTER => PaperSize::Letter,
+        }
+        .dimensions();
+        match self.orientation {
+            PageOrientation::Landscape => (height, width),
+            PageOrientation::Portrait => (width, height),
+        }
+    }
+
+    /// render cover page, date of cover setting falls back to `date`
+    pub fn print_cover(
+        &self,
+        cover: &CoverSetting,
+        labels: &(String, String, String),
+        date: &str,
+        dest: &Path,
+    ) -> anyhow::Result<()> {
+        let data = CoverData {
+            cover: cover.clone(),
+            date: cover.date.clone().unwrap_or(date.into()),
+            labels: labels.clone(),
+            size: self.size.clone(),
+            orientation: self.orientation.css().into(),
+        };
+        self.print_page(&self.render(COVER_TEMPLATE, &data)?, dest)
+    }
+
+    /// render one-page divider with title and optional subtitle
+    pub fn print_divider(&self, divider: &Divider, dest: &Path) -> anyhow::Result<()> {
+        let data = DividerData {
+            divider: divider.clone(),
+            size: self.size.clone(),
+            orientation: self.orientation.css().into(),
+        };
+        self.print_page(&self.render(DIVIDER_TEMPLATE, &data)?, dest)
+    }
+
+    /// html of `template` rendered from `data`
+    fn render<T: Serialize>(&self, template: &str, data: &T) -> anyhow::Result<String> {
+        Ok(self
             .template
-            .render(TOC_TEMPLATE, &Context::from_serialize(&data)?)?
-            .as_bytes()
-            .to_vec();
-        let html_dest = dest.parent().unwrap().join(format!(
-            "{}.html",
-            dest.file_stem().unwrap().to_str().unwrap()
-        ));
-        fs::write(&html_dest, bytes)?;
-        html_to_pdf(&html_dest, &dest)?;
-        Ok(())
+            .render(template, &Context::from_serialize(data)?)?)
+    }
+
+    /// write html next to `dest` as "name.html", then print it into `dest`
+    fn print_page(&self, html: &str, dest: &Path) -> anyhow::Result<()> {
+        let html_dest = dest.with_extension("html");
+        fs::write(&html_dest, html)?;
+        self.print_html(&html_dest, dest)
+    }
+
+    fn print_html(&self, source: &Path, destination: &Path) -> anyhow::Result<()> {
+        match &self.browsers {
+            Some(browsers) => {
+                let name = source.file_name().unwrap_or_default().to_string_lossy();
+                let pdf = browsers.print(&file_url(source), &name, pdf_options)?;
+                fs::write(destination, pdf)?;
+                Ok(())
+            }
+            None => html_to_pdf(source, destination),
+        }
     }
 }
 
 pub fn html_to_pdf(source: &Path, destination: &Path) -> anyhow::Result<()> {
-    let url = format!("file:///{}", source.to_string_lossy().to_string());
-    let browser = Browser::default()?;
+    let url = file_url(source);
+    let browser = browser()?;
     let tab = browser.new_tab()?;
-    let pdf_options: Option<PrintToPdfOptions> = Some(PrintToPdfOptions {
+    let pdf = tab
+        .navigate_to(&url)?
+        .wait_until_navigated()?
+        .print_to_pdf(Some(pdf_options()))?;
+    fs::write(destination, pdf)?;
+    Ok(())
+}
+
+/// print options taking page size from css
+pub fn pdf_options() -> PrintToPdfOptions {
+    PrintToPdfOptions {
         landscape: None,
         display_header_footer: None,
         print_background: None,
@@ -125,11 +334,42 @@ pub fn html_to_pdf(source: &Path, destination: &Path) -> anyhow::Result<()> {
         footer_template: None,
         prefer_css_page_size: Some(true),
         transfer_mode: None,
-    });
-    let pdf = tab
-        .navigate_to(&url)?
-        .wait_until_navigated()?
-        .print_to_pdf(pdf_options)?;
-    fs::write(destination, pdf)?;
-    Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn custom_template_test() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join("fusion_toc_template.html");
+        fs::write(
+            &path,
+            "{{ company }}|{{ study }}|{{ purpose }}|{{ language }}|{{ total_pages }}|\
+             {% for item in items %}{{ item.title }} {{ item.page + 1 }};{% endfor %}",
+        )?;
+        let mut render = Render::new()?;
+        render
+            .set_company("Akeso")
+            .set_study("AK112-303")
+            .set_purpose("CSR")
+            .set_language(&Language::EN)
+            .set_total_pages(12)
+            .set_template(&path)?;
+        let items = vec![Location {
+            id: Some(0),
+            title: "Table 14.1.1".into(),
+            page: 0,
+            path: PathBuf::new(),
+            divider: false,
+            attachment: false,
+            ..Default::default()
+        }];
+        assert_eq!(
+            render.html(&items)?,
+            "Akeso|AK112-303|CSR|EN|12|Table 14.1.1 1;"
+        );
+        fs::remove_file(&path)?;
+        Ok(())
+    }
 }
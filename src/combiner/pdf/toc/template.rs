@@ -8,7 +8,21 @@ pub const TEMPLATE: &str = r#"
     <style>
         body {
             font-family: SimSun, sans-serif;
+            margin: 0;
+        }
+
+        /* pages are laid out in the size of paper, so that overflow measured on screen matches print */
+        .page {
+            box-sizing: border-box;
+            width: {{ page_width | round(precision=2) }}pt;
+            height: calc({{ page_height | round(precision=2) }}pt - 1px);
             padding: 3%;
+            overflow: hidden;
+            page-break-after: always;
+        }
+
+        .page:last-child {
+            page-break-after: auto;
         }
 
         a {
@@ -27,13 +41,9 @@ pub const TEMPLATE: &str = r#"
 
         @media print {
             @page {
-                size: {{ size }} landscape;
+                size: {{ size }} {{ orientation }};
                 margin: 0;
             }
-
-            section {
-                margin-bottom: 50px;
-            }
         }
 
         .dashed-line-container {
@@ -51,32 +61,39 @@ pub const TEMPLATE: &str = r#"
             position: absolute;
             bottom: 5px;
             right: 10px;
-        }
-
-        .break-page {
-            page-break-after: always;
+            padding-left: 4px;
+            background-color: #fff;
         }
 
         .container {
             position: relative;
             display: flex;
         }
+
+        .attachment {
+            background-color: #fff;
+            color: #808080;
+            font-size: 0.8em;
+        }
     </style>
 </head>
 
 <body>
     <div id="toc">
-        <div class="header">
-            <div style="margin-bottom: 5px; display: grid; grid-template-columns: auto auto;">
-                <div>{{ toc_headers.0 }}</div>
-                <div style="text-align: right;">{{ toc_headers.1 }}</div>
-            </div>
-            <div style="display: grid; grid-template-columns: auto auto;">
-                <div>{{ toc_headers.2 }}</div>
-                <div style="text-align: right;">{{ toc_headers.3 }}</div>
-            </div>
-            <div class="item" style="font-weight: bold; font-size: 20px; text-align: center; height: 40px;">{{ content }}
+        <div class="page">
+            <div class="header">
+                <div style="margin-bottom: 5px; display: grid; grid-template-columns: auto auto;">
+                    <div>{{ toc_headers.0 }}</div>
+                    <div style="text-align: right;">{{ toc_headers.1 }}</div>
+                </div>
+                <div style="display: grid; grid-template-columns: auto auto;">
+                    <div>{{ toc_headers.2 }}</div>
+                    <div style="text-align: right;">{{ toc_headers.3 }}</div>
+                </div>
+                <div class="item title-page" style="font-weight: bold; font-size: 20px; text-align: center; height: 40px;">{{ content }}
+                </div>
             </div>
+            <div class="detail"></div>
         </div>
     </div>
 </body>
@@ -88,34 +105,24 @@ pub const TEMPLATE: &str = r#"
                 id: "{{ item.id }}",
                 title: "{{ item.title }}",
                 page: "{{ item.page + 1 }}",
+                divider: {{ item.divider }},
+                attachment: {{ item.attachment }},
+                level: {{ item.level }},
+                heading: {{ item.heading }},
+                range: "{% if item.last_page is number %}{{ item.page + 1 }}-{{ item.last_page + 1 }}{% endif %}",
             },
         {% endfor %}
     ];
 
+    const indent = {{ indent }};
     const toc = document.getElementById("toc");
-    let currentPageTitleNumber = 0;
-    const newHeader = Array.from(document.getElementsByClassName("header")).pop().cloneNode(true);
-    newHeader.style.paddingTop = "4%";
-    newHeader.getElementsByClassName("item")[0].innerText = "";
-
-
-    let detailArea = document.createElement("div");
-    detailArea.className = "detail";
-
-    data.forEach((e, index) => {
-        if (currentPageTitleNumber > 30) {
-            const breaker = document.createElement("div");
-            breaker.className = "break-page";
-            toc.appendChild(detailArea);
-            if (index < data.length) {
-                toc.appendChild(breaker);
-                toc.appendChild(newHeader.cloneNode(true));
-            }
-            currentPageTitleNumber = 0;
-            detailArea = document.createElement("div");
-            detailArea.className = "detail";
-        }
-        currentPageTitleNumber++;
+    let page = toc.getElementsByClassName("page")[0];
+    // headers are repeated on every page, the title is only on the first page
+    const newPage = page.cloneNode(true);
+    newPage.getElementsByClassName("title-page")[0].remove();
+    let detailArea = page.getElementsByClassName("detail")[0];
+
+    data.forEach((e) => {
         const link = document.createElement("a");
         link.id = e.id;
         link.href = '#' + e.id
@@ -125,10 +132,20 @@ pub const TEMPLATE: &str = r#"
 
         const item = document.createElement("div");
         item.className = "item";
+        item.style.paddingLeft = `${e.level * indent}pt`;
         const title = document.createElement("span");
         title.innerHTML = `${e.title}&#8197`;
         title.style.backgroundColor = "\#ffffff";
+        if (e.divider || e.heading) {
+            title.style.fontWeight = "bold";
+        }
         item.appendChild(title);
+        if (e.attachment) {
+            const mark = document.createElement("span");
+            mark.className = "attachment";
+            mark.innerText = "[RTF]";
+            item.appendChild(mark);
+        }
         container.appendChild(item);
 
         itemLineContainer = document.createElement("div");
@@ -142,14 +159,206 @@ pub const TEMPLATE: &str = r#"
 
         const itemPage = document.createElement("div");
         itemPage.className = "page-number";
-        itemPage.innerText = e.page;
+        itemPage.innerText = e.range || e.page;
         container.appendChild(itemPage);
 
         link.appendChild(container);
         detailArea.appendChild(link);
+        // entry which overflows the page moves to a new page, unless it is the only entry on the page
+        if (page.scrollHeight > page.clientHeight && detailArea.children.length > 1) {
+            detailArea.removeChild(link);
+            page = newPage.cloneNode(true);
+            toc.appendChild(page);
+            detailArea = page.getElementsByClassName("detail")[0];
+            detailArea.appendChild(link);
+        }
     });
-    toc.appendChild(detailArea);
 </script>
 
 </html>
 "#;
+
+pub const COVER_TEMPLATE: &str = r#"
+<!DOCTYPE html>
+<html lang="en">
+
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <style>
+        body {
+            font-family: SimSun, sans-serif;
+            margin: 0;
+        }
+
+        @media print {
+            @page {
+                size: {{ size }} {{ orientation }};
+                margin: 0;
+            }
+        }
+
+        .cover {
+            display: flex;
+            flex-direction: column;
+            justify-content: center;
+            align-items: center;
+            height: 100vh;
+            text-align: center;
+        }
+
+        .study-title {
+            font-size: 28px;
+            font-weight: bold;
+            width: 80%;
+            margin-bottom: 40px;
+        }
+
+        .deliverable {
+            font-size: 22px;
+            margin-bottom: 60px;
+        }
+
+        .field {
+            font-size: 16px;
+            margin-bottom: 12px;
+        }
+    </style>
+</head>
+
+<body>
+    <div class="cover">
+        <div class="study-title">{{ cover.study_title }}</div>
+        <div class="deliverable">{{ cover.deliverable }}</div>
+        {% if cover.protocol %}
+        <div class="field">{{ labels.0 }}: {{ cover.protocol }}</div>
+        {% endif %}
+        {% if cover.version %}
+        <div class="field">{{ labels.1 }}: {{ cover.version }}</div>
+        {% endif %}
+        <div class="field">{{ labels.2 }}: {{ date }}</div>
+    </div>
+</body>
+
+</html>
+"#;
+
+pub const DIVIDER_TEMPLATE: &str = r#"
+<!DOCTYPE html>
+<html lang="en">
+
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <style>
+        body {
+            font-family: SimSun, sans-serif;
+            margin: 0;
+        }
+
+        @media print {
+            @page {
+                size: {{ size }} {{ orientation }};
+                margin: 0;
+            }
+        }
+
+        .divider {
+            display: flex;
+            flex-direction: column;
+            justify-content: center;
+            align-items: center;
+            height: 100vh;
+            text-align: center;
+        }
+
+        .title {
+            font-size: 32px;
+            font-weight: bold;
+            width: 80%;
+        }
+
+        .subtitle {
+            font-size: 20px;
+            width: 80%;
+            margin-top: 24px;
+        }
+    </style>
+</head>
+
+<body>
+    <div class="divider">
+        <div class="title">{{ divider.title }}</div>
+        {% if divider.subtitle %}
+        <div class="subtitle">{{ divider.subtitle }}</div>
+        {% endif %}
+    </div>
+</body>
+
+</html>
+"#;
+
+pub const EXPORT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="{{ lang }}">
+
+<head>
+    <meta charset="UTF-8">
+    <title>{{ title }}</title>
+    <style>
+        body {
+            font-family: SimSun, sans-serif;
+            margin: 3%;
+        }
+
+        .header {
+            display: grid;
+            grid-template-columns: auto auto;
+            margin-bottom: 5px;
+        }
+
+        .right {
+            text-align: right;
+        }
+
+        h1 {
+            font-size: 20px;
+            text-align: center;
+        }
+
+        .entry {
+            display: flex;
+            align-items: baseline;
+            margin-bottom: 5px;
+        }
+
+        .heading {
+            font-weight: bold;
+        }
+
+        .leader {
+            flex: 1;
+            margin: 0 4px;
+            border-bottom: 1px dotted #000;
+        }
+    </style>
+</head>
+
+<body>
+    <div class="header">
+        <div>{{ toc_headers.0 }}</div>
+        <div class="right">{{ toc_headers.1 }}</div>
+        <div>{{ toc_headers.2 }}</div>
+        <div class="right">{{ toc_headers.3 }}</div>
+    </div>
+    <h1>{{ title }}</h1>
+    {% for entry in entries %}
+    <div class="entry{% if entry.heading %} heading{% endif %}" style="padding-left: {{ entry.level * indent }}pt;">
+        <span>{{ entry.title }}</span>
+        <span class="leader"></span>
+        <span>{{ entry.page }}</span>
+    </div>
+    {% endfor %}
+</body>
+
+</html>
+"#;
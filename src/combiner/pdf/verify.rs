@@ -0,0 +1,393 @@
+use super::{location::Location, merge::page_ids};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub enum Status {
+    Passed,
+    /// destination does not point at a page of combined pdf
+    MissingPage,
+    /// text of target page does not contain the expected title
+    TitleMismatch,
+    /// text of target page could not be extracted, title is not checked
+    TextUnavailable,
+    /// link destination was never resolved to a page
+    Orphaned,
+}
+
+/// a toc link or bookmark
+#[derive(Debug, Serialize, Clone)]
+pub struct Target {
+    pub title: String,
+    /// page index in combined pdf, starting from 0
+    pub page: Option<usize>,
+    pub status: Status,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PageCount {
+    pub expected: usize,
+    pub actual: usize,
+    pub passed: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Report {
+    pub passed: bool,
+    pub pages: PageCount,
+    pub links: Vec<Target>,
+    pub bookmarks: Vec<Target>,
+}
+
+impl Report {
+    /// targets failed in checks, text unavailable is not regarded as failure
+    pub fn failures(&self) -> usize {
+        self.links
+            .iter()
+            .chain(self.bookmarks.iter())
+            .filter(|t| !matches!(t.status, Status::Passed | Status::TextUnavailable))
+            .count()
+    }
+}
+
+/// check links in toc pages (pages of locations without id) and bookmarks of combined pdf
+pub fn verify(
+    doc: &Document,
+    locations: &[Location],
+    total_pages: usize,
+) -> anyhow::Result<Report> {
+    let page_ids = page_ids(doc);
+    let pages = page_ids
+        .iter()
+        .map(|(index, id)| (*id, *index))
+        .collect::<HashMap<ObjectId, usize>>();
+    let mut checker = Checker {
+        doc,
+        pages,
+        locations,
+        texts: HashMap::new(),
+        visited: HashSet::new(),
+    };
+
+    let mut links = vec![];
+    let mut toc_pages = vec![];
+    for (index, location) in locations.iter().enumerate() {
+        if location.id.is_some() {
+            continue;
+        }
+        let end = locations
+            .get(index + 1)
+            .map_or(total_pages, |next| next.page);
+        toc_pages.extend(location.page..end);
+    }
+    for page in toc_pages {
+        let page_id = match page_ids.get(&page) {
+            Some(id) => *id,
+            None => continue,
+        };
+        for annotation in doc.get_page_annotations(page_id).unwrap_or_default() {
+            if annotation.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Link".as_slice())
+            {
+                continue;
+            }
+            if let Some(target) = checker.link(annotation) {
+                links.push(target);
+            }
+        }
+    }
+
+    let mut bookmarks = vec![];
+    if let Ok(outlines) = doc
+        .catalog()
+        .and_then(|c| c.get(b"Outlines"))
+        .and_then(|o| doc.dereference(o))
+        .and_then(|(_, o)| o.as_dict())
+    {
+        checker.bookmarks(outlines, &mut bookmarks);
+    }
+
+    let actual = page_ids.len();
+    let pages = PageCount {
+        expected: total_pages,
+        actual,
+        passed: total_pages == actual,
+    };
+    let mut report = Report {
+        passed: false,
+        pages,
+        links,
+        bookmarks,
+    };
+    report.passed = report.pages.passed && report.failures() == 0;
+    Ok(report)
+}
+
+struct Checker<'a> {
+    doc: &'a Document,
+    /// page object to page index
+    pages: HashMap<ObjectId, usize>,
+    locations: &'a [Location],
+    /// extracted text of pages, None if extraction failed
+    texts: HashMap<usize, Option<String>>,
+    /// outline items already checked, guards against cyclic outline items
+    visited: HashSet<ObjectId>,
+}
+
+impl Checker<'_> {
+    fn link(&mut self, annotation: &Dictionary) -> Option<Target> {
+        let dest = match annotation.get(b"Dest") {
+            Ok(dest) => dest,
+            Err(_) => match annotation
+                .get(b"A")
+                .and_then(|a| self.doc.dereference(a))
+                .and_then(|(_, a)| a.as_dict())
+            {
+                // links to other files or uris are not part of toc
+                Ok(action)
+                    if action.get(b"S").and_then(Object::as_name).ok()
+                        == Some(b"GoTo".as_slice()) =>
+                {
+                    action.get(b"D").ok()?
+                }
+                _ => return None,
+            },
+        };
+        let page = match self.destination_page(dest) {
+            Ok(page) => page,
+            Err(title) => {
+                return Some(Target {
+                    title,
+                    page: None,
+                    status: Status::Orphaned,
+                })
+            }
+        };
+        let title = match page {
+            Some(page) => self
+                .locations
+                .iter()
+                .filter(|l| l.id.is_some())
+                .find(|l| l.page == page)
+                .map(|l| l.title.clone())
+                .unwrap_or_default(),
+            None => String::new(),
+        };
+        Some(self.check(&title, page))
+    }
+
+    fn bookmarks(&mut self, parent: &Dictionary, result: &mut Vec<Target>) {
+        let mut current = parent.get(b"First").and_then(Object::as_reference).ok();
+        while let Some(id) = current {
+            if !self.visited.insert(id) {
+                break;
+            }
+            let item = match self.doc.get_dictionary(id) {
+                Ok(item) => item,
+                Err(_) => break,
+            };
+            let title = item
+                .get(b"Title")
+                .and_then(Object::as_str)
+                .map(decode_text)
+                .unwrap_or_default();
+            let page = item
+                .get(b"Dest")
+                .ok()
+                .and_then(|dest| self.destination_page(dest).ok())
+                .flatten();
+            // only bookmarks of outputs carry their titles, group and section nodes are checked for pages only
+            let is_output = self
+                .locations
+                .iter()
+                .any(|l| l.id.is_some() && l.title.eq(&title));
+            let mut target = self.check(if is_output { &title } else { "" }, page);
+            target.title = title;
+            result.push(target);
+            self.bookmarks(item, result);
+            current = item.get(b"Next").and_then(Object::as_reference).ok();
+        }
+    }
+
+    /// page index of destination, Ok(None) if page is not found, Err with name if destination is unresolved
+    fn destination_page(&self, dest: &Object) -> Result<Option<usize>, String> {
+        match self.doc.dereference(dest).map(|(_, d)| d) {
+            Ok(Object::Array(array)) => Ok(array
+                .first()
+                .and_then(|page| page.as_reference().ok())
+                .and_then(|page| self.pages.get(&page).copied())),
+            Ok(Object::Name(name)) | Ok(Object::String(name, _)) => {
+                Err(String::from_utf8_lossy(name).to_string())
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// check target page exists and contains title, empty title is not checked
+    fn check(&mut self, title: &str, page: Option<usize>) -> Target {
+        let status = match page {
+            None => Status::MissingPage,
+            Some(_) if title.is_empty() => Status::Passed,
+            Some(page) => match self.text(page) {
+                Some(text) if text.contains(&normalize(title)) => Status::Passed,
+                Some(_) => Status::TitleMismatch,
+                None => Status::TextUnavailable,
+            },
+        };
+        Target {
+            title: title.into(),
+            page,
+            status,
+        }
+    }
+
+    fn text(&mut self, page: usize) -> Option<String> {
+        let doc = self.doc;
+        self.texts
+            .entry(page)
+            .or_insert_with(|| {
+                doc.extract_text(&[page as u32 + 1])
+                    .ok()
+                    .map(|text| normalize(&text))
+                    .filter(|text| !text.is_empty())
+            })
+            .clone()
+    }
+}
+
+/// remove whitespaces, titles wrap into several lines in outputs
+fn normalize(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// decode pdf text string, UTF-16BE with BOM or PDFDocEncoding treated as latin-1
+fn decode_text(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xFE, 0xFF]) {
+        Some(utf16) => String::from_utf16_lossy(
+            &utf16
+                .chunks(2)
+                .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]))
+                .collect::<Vec<u16>>(),
+        ),
+        None => bytes.iter().map(|b| *b as char).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+
+    #[test]
+    fn verify_test() -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        let mut kids = vec![];
+        for text in [
+            "Contents",
+            "Table 14.1.1: Demographic",
+            "Table 14.3.1: Adverse Events",
+        ] {
+            let content = format!("BT /F1 12 Tf 72 720 Td ({}) Tj ET", text);
+            let contents = doc.add_object(Stream::new(dictionary! {}, content.into_bytes()));
+            kids.push(doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => contents,
+                "Resources" => dictionary! { "Font" => dictionary! { "F1" => font } },
+            }));
+        }
+        let missing = doc.new_object_id();
+        let annots = [
+            Object::Array(vec![kids[1].into(), "XYZ".into()]),
+            Object::Array(vec![kids[2].into(), "XYZ".into()]),
+            Object::Array(vec![missing.into(), "XYZ".into()]),
+            Object::Name(b"output9".to_vec()),
+        ]
+        .into_iter()
+        .map(|dest| {
+            doc.add_object(dictionary! { "Type" => "Annot", "Subtype" => "Link", "Dest" => dest })
+                .into()
+        })
+        .collect::<Vec<Object>>();
+        doc.get_dictionary_mut(kids[0])?.set("Annots", annots);
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids.iter().map(|id| (*id).into()).collect::<Vec<Object>>(),
+                "Count" => 3,
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            }),
+        );
+        // bookmark whose "Next" points at itself
+        let outlines = doc.new_object_id();
+        let bookmark = doc.add_object(dictionary! {
+            "Title" => Object::string_literal("Table 14.1.1: Demographic"),
+            "Parent" => outlines,
+            "Dest" => vec![kids[1].into(), "XYZ".into()],
+        });
+        doc.get_dictionary_mut(bookmark)?.set("Next", bookmark);
+        doc.objects.insert(
+            outlines,
+            Object::Dictionary(dictionary! { "Type" => "Outlines", "First" => bookmark }),
+        );
+        let catalog = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "Outlines" => outlines,
+        });
+        doc.trailer.set("Root", catalog);
+
+        let location = |id: Option<usize>, title: &str, page: usize| Location {
+            id,
+            title: title.into(),
+            page,
+            ..Default::default()
+        };
+        let locations = [
+            location(None, "Contents", 0),
+            location(Some(0), "Table 14.1.1: Demographic", 1),
+            location(Some(1), "Table 14.2.1: Efficacy", 2),
+        ];
+        let report = verify(&doc, &locations, 4)?;
+        let statuses = report
+            .links
+            .iter()
+            .map(|link| link.status.clone())
+            .collect::<Vec<Status>>();
+        assert_eq!(
+            statuses,
+            [
+                Status::Passed,
+                Status::TitleMismatch,
+                Status::MissingPage,
+                Status::Orphaned
+            ]
+        );
+        assert_eq!(report.links[3].title, "output9");
+        assert_eq!(report.bookmarks.len(), 1);
+        assert_eq!(report.bookmarks[0].status, Status::Passed);
+        assert!(!report.pages.passed);
+        assert_eq!((report.pages.expected, report.pages.actual), (4, 3));
+        assert_eq!(report.failures(), 3);
+        assert!(!report.passed);
+        Ok(())
+    }
+
+    #[test]
+    fn decode_text_test() {
+        assert_eq!(decode_text(b"Tables"), "Tables");
+        assert_eq!(decode_text(&[0xFE, 0xFF, 0x88, 0x68, 0x68, 0x3C]), "表格");
+        assert_eq!(
+            normalize("Table 14.1.1:\n Demographic"),
+            "Table14.1.1:Demographic"
+        );
+    }
+}
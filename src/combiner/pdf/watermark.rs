@@ -0,0 +1,122 @@
+use super::{
+    merge::page_ids,
+    overlay::{add_resource, overlay, OverlayFont, VisualPage},
+};
+use crate::config::{
+    combine::PDFFile,
+    pdf::{WatermarkPages, WatermarkSetting},
+    utils::Language,
+};
+use lopdf::{content::Operation, dictionary, Document};
+
+const WATERMARK_FONT: &str = "FusionWatermark";
+const WATERMARK_STATE: &str = "FusionWatermarkGS";
+
+/// draw watermark text rotated around the center of pages, body pages start from the first page of the first output
+pub fn watermark(
+    doc: &mut Document,
+    files: &[PDFFile],
+    setting: &WatermarkSetting,
+    language: &Language,
+) -> anyhow::Result<()> {
+    if setting.text.is_empty() {
+        return Ok(());
+    }
+    let start = match setting.pages {
+        WatermarkPages::All => 0,
+        WatermarkPages::Body => match files.first() {
+            Some(file) => file.page_actual,
+            None => return Ok(()),
+        },
+    };
+    let font = OverlayFont::new(
+        doc,
+        WATERMARK_FONT,
+        &setting.font,
+        setting.font_size,
+        &setting.text,
+        language,
+    );
+    let opacity = setting.opacity.clamp(0.0, 1.0);
+    let state = doc.add_object(dictionary! {
+        "Type" => "ExtGState",
+        "ca" => opacity,
+        "CA" => opacity,
+    });
+    let state_name = format!("{}{}", WATERMARK_STATE, state.0);
+    let (sin, cos) = setting.angle.to_radians().sin_cos();
+    let width = font.text_width(&setting.text);
+    for (_, page_id) in page_ids(doc).range(start..) {
+        let visual = VisualPage::new(doc, *page_id)?;
+        add_resource(doc, *page_id, b"ExtGState", &state_name, state)?;
+        overlay(
+            doc,
+            *page_id,
+            Some(&font),
+            vec![
+                visual.transform(),
+                Operation::new("gs", vec![state_name.as_str().into()]),
+                Operation::new("g", vec![setting.gray.into()]),
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec![font.name.as_str().into(), font.size.into()]),
+                Operation::new(
+                    "Tm",
+                    vec![
+                        cos.into(),
+                        sin.into(),
+                        (-sin).into(),
+                        cos.into(),
+                        (visual.width / 2.0).into(),
+                        (visual.height / 2.0).into(),
+                    ],
+                ),
+                // center text on origin, cap height is roughly 0.7em
+                Operation::new(
+                    "Td",
+                    vec![(-width / 2.0).into(), (-font.size * 0.35).into()],
+                ),
+                Operation::new("Tj", vec![font.encode(&setting.text)]),
+                Operation::new("ET", vec![]),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{Object, Stream};
+    #[test]
+    fn watermark_test() -> anyhow::Result<()> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let content = doc.add_object(Stream::new(dictionary! {}, b"0 0 m".to_vec()));
+        let page = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            "Contents" => content,
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog);
+        watermark(&mut doc, &[], &WatermarkSetting::default(), &Language::EN)?;
+        let page = doc.get_dictionary(page)?;
+        assert_eq!(page.get(b"Contents")?.as_array()?.len(), 3);
+        let states = page
+            .get(b"Resources")?
+            .as_dict()?
+            .get(b"ExtGState")?
+            .as_dict()?;
+        assert_eq!(states.len(), 1);
+        Ok(())
+    }
+}
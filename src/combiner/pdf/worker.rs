@@ -1,7 +1,6 @@
-use super::combiner::PDFCombiner;
+use super::{combiner::PDFCombiner, toc::pool::BrowserPool};
 use crate::config::combine::CombinePDFParam;
 use std::{
-    path::Path,
     sync::{mpsc, Arc, Mutex},
     thread,
     time::Duration,
@@ -14,12 +13,12 @@ pub struct PDFCombineWorker {
 impl PDFCombineWorker {
     pub fn new(
         id: usize,
-        bin: &Path,
         receiver: Arc<Mutex<mpsc::Receiver<CombinePDFParam>>>,
+        browsers: Arc<BrowserPool>,
         status: Arc<Mutex<mpsc::Sender<()>>>,
         logger: Arc<Mutex<mpsc::Sender<String>>>,
     ) -> Self {
-        let bin = bin.to_owned();
+        let browsers = Some(browsers);
         let handler = thread::spawn(move || {
             logger
                 .lock()
@@ -40,9 +39,12 @@ impl PDFCombineWorker {
                                 filename.to_string()
                             ))
                             .ok();
-                        match PDFCombiner::new(&param, &bin) {
+                        match PDFCombiner::new(&param, &browsers) {
                             Ok(mut combiner) => match combiner.combine() {
                                 Ok(_) => {
+                                    for log in combiner.logs() {
+                                        logger.lock().unwrap().send(format!("{}\n", log)).ok();
+                                    }
                                     status.lock().unwrap().send(()).ok();
                                     logger
                                         .lock()
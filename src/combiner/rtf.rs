@@ -1,3 +1,4 @@
 pub mod combiner;
 pub mod controller;
+pub mod tokenizer;
 pub mod worker;
@@ -1,114 +1,502 @@
+use super::tokenizer::{group_end, tokenize, write, Token};
 use crate::config::combine::RTFCombineParam;
-use std::{
-    fs::{read, remove_file, OpenOptions},
-    io::Write,
-    ops::Sub,
-};
+use anyhow::anyhow;
+use std::{collections::HashMap, fs};
 
-const PAGE_PAR: &'static [u8] = br"{\page\par}";
-const WINDOW_CTRL: &'static [u8] = br"\widowctrl";
+const PAGE_PAR: &[u8] = br"{\page\par}";
+/// control words before tables in header, such as "\ansicpg1252"
+const PREAMBLE: [&str; 17] = [
+    "rtf",
+    "ansi",
+    "mac",
+    "pc",
+    "pca",
+    "ansicpg",
+    "deff",
+    "adeff",
+    "deflang",
+    "deflangfe",
+    "adeflang",
+    "uc",
+    "fbidis",
+    "stshfdbch",
+    "stshfloch",
+    "stshfhich",
+    "stshfbi",
+];
+/// destinations of header other than font, color and style tables, kept from the first file only
+const HEADER_GROUPS: [&str; 8] = [
+    "filetbl",
+    "listtable",
+    "listoverridetable",
+    "revtbl",
+    "rsidtbl",
+    "info",
+    "pgdsctbl",
+    "xmlnstbl",
+];
+const FONT_WORDS: [&str; 4] = ["f", "af", "deff", "adeff"];
+const COLOR_WORDS: [&str; 14] = [
+    "cf",
+    "cb",
+    "chcbpat",
+    "chcfpat",
+    "highlight",
+    "cbpat",
+    "cfpat",
+    "clcbpat",
+    "clcfpat",
+    "brdrcf",
+    "trcbpat",
+    "trcfpat",
+    "ulc",
+    "chshdng",
+];
+const STYLE_WORDS: [&str; 7] = ["s", "cs", "ds", "ts", "sbasedon", "snext", "slink"];
+/// control words numbering paragraph, character, section and table styles
+const STYLE_TYPES: [&str; 4] = ["s", "cs", "ds", "ts"];
 
-/// combine muliple rtfs into one rtf
+/// entry of font table or stylesheet
+#[derive(Debug, Clone)]
+struct Entry<'a> {
+    number: i64,
+    /// tokens of entry without its number, to find the same definition in other files
+    key: Vec<u8>,
+    tokens: Vec<Token<'a>>,
+}
+
+/// rtf split into header, tables and body
+#[derive(Debug, Default)]
+struct Parts<'a> {
+    /// header tokens from "{\rtf1" to the body, tables are replaced by merged tables
+    header: Vec<Token<'a>>,
+    /// position of the first table in header
+    tables_at: Option<usize>,
+    fonts: Vec<Entry<'a>>,
+    colors: Vec<Vec<Token<'a>>>,
+    styles: Vec<Entry<'a>>,
+    has_fonts: bool,
+    has_colors: bool,
+    has_styles: bool,
+    body: Vec<Token<'a>>,
+}
+
+/// numbers of fonts, colors and styles of one file in combined rtf
+#[derive(Debug, Default)]
+struct Numbers {
+    fonts: HashMap<i64, i64>,
+    colors: HashMap<i64, i64>,
+    styles: HashMap<i64, i64>,
+}
+
+/// combine muliple rtfs into one rtf, font, color and style tables of all files are merged into the header
+/// of the first file, and references in bodies are renumbered to the merged tables
+///
+/// other header destinations such as list tables are taken from the first file only, files which are
+/// not rtf are rejected
 pub fn combine(param: &RTFCombineParam) -> anyhow::Result<()> {
-    // create destination file
-    if param.destination.exists() {
-        remove_file(&param.destination)?;
-    }
-    let mut destination = OpenOptions::new()
-        .create_new(true)
-        .write(true)
-        .open(&param.destination)?;
-
-    // record if header is writen
-    let mut header_writen = false;
-    for (index, p) in param.files.iter().enumerate() {
-        let data = read(p)?;
-        if let Some((start, end)) = extract_file_content(&data) {
-            if !header_writen {
-                destination.write(data.get(0..start).unwrap())?;
-                header_writen = true;
+    let data = param
+        .files
+        .iter()
+        .map(fs::read)
+        .collect::<Result<Vec<Vec<u8>>, _>>()?;
+    let mut files = data
+        .iter()
+        .zip(&param.files)
+        .map(|(data, path)| {
+            split(tokenize(data)).ok_or(anyhow!("{} is not an rtf file", path.display()))
+        })
+        .collect::<anyhow::Result<Vec<Parts>>>()?;
+
+    let mut fonts: Vec<Entry> = vec![];
+    let mut colors: Vec<Vec<Token>> = vec![];
+    let mut styles: Vec<Entry> = vec![];
+    for file in files.iter_mut() {
+        let mut numbers = Numbers::default();
+        for (index, color) in file.colors.iter().enumerate() {
+            let color_key = key(color);
+            let target = match colors.iter().position(|c| key(c).eq(&color_key)) {
+                Some(target) => target,
+                None => {
+                    colors.push(color.clone());
+                    colors.len() - 1
+                }
+            };
+            numbers.colors.insert(index as i64, target as i64);
+        }
+        let added = fonts.len();
+        numbers.fonts = merge_entries(&mut fonts, &file.fonts);
+        for font in fonts.iter_mut().skip(added) {
+            renumber(&mut font.tokens, &numbers);
+        }
+        // fonts and colors in styles are renumbered before styles are compared
+        let mut own_styles = file.styles.clone();
+        for style in own_styles.iter_mut() {
+            renumber(&mut style.tokens, &numbers);
+            style.key = style_key(&style.tokens);
+        }
+        let added = styles.len();
+        let style_numbers = Numbers {
+            styles: merge_entries(&mut styles, &own_styles),
+            ..Default::default()
+        };
+        for style in styles.iter_mut().skip(added) {
+            renumber(&mut style.tokens, &style_numbers);
+        }
+        numbers.styles = style_numbers.styles;
+        renumber(&mut file.header, &numbers);
+        renumber(&mut file.body, &numbers);
+    }
+
+    let mut out = vec![];
+    if let Some(first) = files.first() {
+        let tables_at = first.tables_at.unwrap_or(first.header.len());
+        write(&first.header[..tables_at], &mut out);
+        if files.iter().any(|f| f.has_fonts) {
+            write_table(&mut out, "fonttbl", fonts.iter().map(|f| &f.tokens), true);
+        }
+        if files.iter().any(|f| f.has_colors) {
+            write_table(&mut out, "colortbl", colors.iter(), false);
+        }
+        if files.iter().any(|f| f.has_styles) {
+            write_table(
+                &mut out,
+                "stylesheet",
+                styles.iter().map(|s| &s.tokens),
+                true,
+            );
+        }
+        write(&first.header[tables_at..], &mut out);
+    }
+    for (index, file) in files.iter().enumerate() {
+        // merged header keeps "\deffN" of the first file, later bodies start from their own default font
+        if let Some(font) = default_font(&file.header).filter(|_| index > 0) {
+            let reset = [
+                Token::Control {
+                    name: "plain",
+                    param: None,
+                    space: false,
+                },
+                Token::Control {
+                    name: "f",
+                    param: Some(font),
+                    space: true,
+                },
+            ];
+            write(&reset, &mut out);
+        }
+        write(&file.body, &mut out);
+        if index < files.len() - 1 {
+            out.extend_from_slice(PAGE_PAR);
+        }
+    }
+    out.push(b'}');
+    fs::write(&param.destination, out)?;
+    Ok(())
+}
+
+/// split rtf into header, tables and body, the body starts from the first control word or group of
+/// the root group which is not part of header, such as "\widowctrl"
+fn split(tokens: Vec<Token>) -> Option<Parts> {
+    if tokens.first() != Some(&Token::GroupStart) || !tokens.get(1)?.is("rtf") {
+        return None;
+    }
+    let root_end = group_end(&tokens, 0);
+    let mut parts = Parts::default();
+    let mut index = 1;
+    parts.header.push(Token::GroupStart);
+    while index < root_end {
+        match &tokens[index] {
+            Token::GroupStart => {
+                let end = group_end(&tokens, index);
+                let ignorable = tokens.get(index + 1) == Some(&Token::Symbol(br"\*"));
+                let destination = match tokens.get(if ignorable { index + 2 } else { index + 1 }) {
+                    Some(Token::Control { name, .. }) => *name,
+                    _ => "",
+                };
+                let inner = &tokens[(index + 2).min(end)..end];
+                match destination {
+                    "fonttbl" => {
+                        parts.fonts = group_entries(inner).into_iter().map(font_entry).collect();
+                        parts.has_fonts = true;
+                    }
+                    "colortbl" => {
+                        parts.colors = color_entries(inner);
+                        parts.has_colors = true;
+                    }
+                    "stylesheet" => {
+                        parts.styles = group_entries(inner).into_iter().map(style_entry).collect();
+                        parts.has_styles = true;
+                    }
+                    _ if ignorable || HEADER_GROUPS.contains(&destination) => {
+                        parts.header.extend_from_slice(&tokens[index..=end]);
+                        index = end + 1;
+                        continue;
+                    }
+                    _ => break,
+                }
+                parts.tables_at.get_or_insert(parts.header.len());
+                index = end + 1;
             }
-            destination.write(data.get(start..end).unwrap())?;
-            if index.lt(&param.files.len().sub(&1)) {
-                destination.write(&PAGE_PAR)?;
+            Token::Control { name, .. } if PREAMBLE.contains(name) => {
+                parts.header.push(tokens[index].clone());
+                index += 1;
             }
+            Token::Text(text) if text.iter().all(|c| c.is_ascii_whitespace()) => {
+                parts.header.push(tokens[index].clone());
+                index += 1;
+            }
+            _ => break,
         }
     }
-    destination.write(br"}")?;
-    Ok(())
+    parts.body = tokens[index.min(root_end)..root_end].to_vec();
+    Some(parts)
 }
 
-/// extract content of rtf, start from symbol "\widowctrl", end with the next to last charater
-/// ```rust
-/// #[test]
-/// fn extract_test() {
-///     let data = br"\widowctrl\test}}";
-///     let result = extract_file_content(&data);
-///     assert_eq(result, Some((0, data.len() - 3)));
-/// }
-/// ```
-fn extract_file_content(data: &[u8]) -> Option<(usize, usize)> {
-    let mut last_curly_brace = data.len() - 1;
-    // seek the last curly brace
-    while last_curly_brace.gt(&0) {
-        if let Some(char) = data.get(last_curly_brace) {
-            if char.eq(&b'}') {
-                break;
+/// entries of font table or stylesheet, either groups such as "{\f0 Times;}" or ungrouped
+/// definitions ending with ";"
+fn group_entries<'a>(tokens: &[Token<'a>]) -> Vec<Vec<Token<'a>>> {
+    let mut entries = vec![];
+    let mut current = vec![];
+    let mut index = 0;
+    while index < tokens.len() {
+        match &tokens[index] {
+            Token::GroupStart if current.is_empty() => {
+                let end = group_end(tokens, index);
+                entries.push(tokens[index + 1..end.min(tokens.len())].to_vec());
+                index = end + 1;
+                continue;
             }
-            last_curly_brace -= 1;
+            Token::Text(text)
+                if current.is_empty() && text.iter().all(|c| c.is_ascii_whitespace()) => {}
+            Token::Text(text) if text.contains(&b';') => {
+                current.push(tokens[index].clone());
+                entries.push(std::mem::take(&mut current));
+            }
+            token => current.push(token.clone()),
         }
+        index += 1;
     }
-    match pattern_position(&WINDOW_CTRL, &data, 0) {
-        Some((start, _)) => Some((start, last_curly_brace)),
-        None => None,
+    entries
+}
+
+/// entries of color table separated by ";", the first entry is usually empty for the auto color
+fn color_entries<'a>(tokens: &[Token<'a>]) -> Vec<Vec<Token<'a>>> {
+    let mut entries = vec![];
+    let mut current = vec![];
+    for token in tokens {
+        match token {
+            Token::Text(text) => {
+                for _ in text.iter().filter(|c| c.eq(&&b';')) {
+                    entries.push(std::mem::take(&mut current));
+                }
+            }
+            token => current.push(token.clone()),
+        }
     }
+    entries
 }
 
-/// find out the position of specify pattern in occurs for the first time
-///
-/// @pattern: the pattern you want to find out
-///
-/// @source: target that you want to find out group
-///
-/// @pointer: the start position in the source
-///
-/// ```rust
-/// #[test]
-/// fn test_pattern_position() {
-///     let content = br"{\fonttbl{\f1\froman\fprq2\fcharset0 SimSun;}".to_vec();
-///     let pattern = br"\fonttbl".to_vec();
-///     let result = pattern_position(&pattern, &content, 0).unwrap();
-///     let result = String::from_utf8(content.get(result.0..result.1).unwrap().into()).unwrap();
-///     assert_eq!(pattern, result.as_bytes());
-///     let pattern = br"\test".to_vec();
-///     let result = pattern_position(&pattern, &content, 0);
-///     assert_eq!(result, None);
-/// }
-/// ```
-pub fn pattern_position(pattern: &[u8], source: &[u8], pointer: usize) -> Option<(usize, usize)> {
-    let mut pointer = pointer;
-    let pattern_size = pattern.len();
-    if pointer > source.len() {
-        return None;
+fn font_entry(tokens: Vec<Token>) -> Entry {
+    let number = tokens
+        .iter()
+        .find_map(|token| match token {
+            Token::Control {
+                name: "f", param, ..
+            } => *param,
+            _ => None,
+        })
+        .unwrap_or(0);
+    let key = key(&tokens
+        .iter()
+        .filter(|token| !token.is("f"))
+        .cloned()
+        .collect::<Vec<Token>>());
+    Entry {
+        number,
+        key,
+        tokens,
     }
-    while pointer < source.len() {
-        if pointer < pattern_size + 1 {
-            pointer += 1;
+}
+
+/// styles without number are paragraph style 0, such as "{\ql Normal;}", which is numbered as "\s0"
+/// so that it can be renumbered
+fn style_entry(mut tokens: Vec<Token>) -> Entry {
+    let number = tokens.iter().find_map(|token| match token {
+        Token::Control { name, param, .. } if STYLE_TYPES.contains(name) => *param,
+        _ => None,
+    });
+    if number.is_none() {
+        tokens.insert(
+            0,
+            Token::Control {
+                name: "s",
+                param: Some(0),
+                space: true,
+            },
+        );
+    }
+    Entry {
+        number: number.unwrap_or(0),
+        key: style_key(&tokens),
+        tokens,
+    }
+}
+
+fn style_key(tokens: &[Token]) -> Vec<u8> {
+    key(&tokens
+        .iter()
+        .map(|token| match token {
+            Token::Control { name, .. } if STYLE_TYPES.contains(name) => Token::Control {
+                name,
+                param: None,
+                space: false,
+            },
+            token => token.clone(),
+        })
+        .collect::<Vec<Token>>())
+}
+
+/// tokens compared across files, spaces delimiting control words and whitespace are ignored
+fn key(tokens: &[Token]) -> Vec<u8> {
+    let mut out = vec![];
+    for token in tokens {
+        match token {
+            Token::Control { name, param, .. } => Token::Control {
+                name,
+                param: *param,
+                space: false,
+            }
+            .write(&mut out),
+            Token::Text(text) => out.extend(text.iter().filter(|c| !c.is_ascii_whitespace())),
+            token => token.write(&mut out),
+        }
+    }
+    out
+}
+
+/// add entries not defined in `merged`, keeping their numbers unless taken, return numbers of entries in
+/// merged table, added entries are renumbered by the caller
+fn merge_entries<'a>(merged: &mut Vec<Entry<'a>>, entries: &[Entry<'a>]) -> HashMap<i64, i64> {
+    let mut numbers = HashMap::new();
+    for entry in entries {
+        if let Some(existing) = merged.iter().find(|m| m.key.eq(&entry.key)) {
+            numbers.insert(entry.number, existing.number);
             continue;
         }
-        let start = pointer - pattern_size - 1;
-        let end = pointer - 1;
-        match source.get(start..end) {
-            Some(target) => {
-                if pattern.eq(target) {
-                    return Some((start, end));
-                }
-            }
-            None => {
+        let number = match merged.iter().any(|m| m.number == entry.number) {
+            true => merged.iter().map(|m| m.number).max().unwrap_or(0) + 1,
+            false => entry.number,
+        };
+        numbers.insert(entry.number, number);
+        merged.push(Entry {
+            number,
+            ..entry.clone()
+        });
+    }
+    numbers
+}
+
+/// number of "\deffN" in header, renumbered to merged font table
+fn default_font(header: &[Token]) -> Option<i64> {
+    header.iter().find_map(|token| match token {
+        Token::Control {
+            name: "deff",
+            param,
+            ..
+        } => *param,
+        _ => None,
+    })
+}
+
+/// replace numbers of fonts, colors and styles
+fn renumber(tokens: &mut [Token], numbers: &Numbers) {
+    for token in tokens.iter_mut() {
+        if let Token::Control {
+            name,
+            param: Some(param),
+            ..
+        } = token
+        {
+            let map = if FONT_WORDS.contains(name) {
+                &numbers.fonts
+            } else if COLOR_WORDS.contains(name) {
+                &numbers.colors
+            } else if STYLE_WORDS.contains(name) {
+                &numbers.styles
+            } else {
                 continue;
+            };
+            if let Some(number) = map.get(param) {
+                *param = *number;
             }
-        };
-        pointer += 1;
+        }
+    }
+}
+
+fn write_table<'a, 'b: 'a>(
+    out: &mut Vec<u8>,
+    word: &str,
+    entries: impl Iterator<Item = &'a Vec<Token<'b>>>,
+    grouped: bool,
+) {
+    out.push(b'{');
+    Token::Control {
+        name: word,
+        param: None,
+        space: false,
+    }
+    .write(out);
+    for entry in entries {
+        if grouped {
+            out.push(b'{');
+        }
+        write(entry, out);
+        out.push(if grouped { b'}' } else { b';' });
+    }
+    out.push(b'}');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn combine_test() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join("fusion_rtf_combine");
+        fs::create_dir_all(&dir)?;
+        let first = dir.join("first.rtf");
+        let second = dir.join("second.rtf");
+        fs::write(
+            &first,
+            br"{\rtf1\ansi\deff0{\fonttbl{\f0\froman Times;}{\f1\fmodern Courier;}}{\colortbl;\red255\green0\blue0;}{\stylesheet{\ql\f0 Normal;}{\s1\f1\cf1\sbasedon0 Heading;}}\widowctrl\s1\f1\cf1 first\par}",
+        )?;
+        fs::write(
+            &second,
+            br"{\rtf1\ansi\deff0{\fonttbl{\f0\fmodern Courier;}{\f1\fswiss Arial;}}{\colortbl;\red0\green0\blue255;\red255\green0\blue0;}{\stylesheet{\ql\f0 Normal;}{\s1\f1\cf2\sbasedon0 Heading;}}\widowctrl\s1\f1\cf1 second\f0\cf2 x\par}",
+        )?;
+        let destination = dir.join("combined.rtf");
+        combine(&RTFCombineParam {
+            destination: destination.clone(),
+            files: vec![first, second],
+        })?;
+        let combined = String::from_utf8(fs::read(&destination)?)?;
+        assert!(combined.starts_with(
+            r"{\rtf1\ansi\deff0{\fonttbl{\f0\froman Times;}{\f1\fmodern Courier;}{\f2\fswiss Arial;}}{\colortbl;\red255\green0\blue0;\red0\green0\blue255;}"
+        ));
+        // normal style of second file uses courier, so it is a new style
+        assert!(combined.contains(r"{\stylesheet{\s0 \ql\f0 Normal;}{\s1\f1\cf1\sbasedon0 Heading;}{\s2 \ql\f1 Normal;}{\s3\f2\cf1\sbasedon2 Heading;}}"));
+        assert!(combined.ends_with(
+            r"\widowctrl\s1\f1\cf1 first\par{\page\par}\plain\f1 \widowctrl\s3\f2\cf2 second\f1\cf1 x\par}"
+        ));
+
+        let text = dir.join("notes.txt");
+        fs::write(&text, "not rtf")?;
+        let error = combine(&RTFCombineParam {
+            destination: destination.clone(),
+            files: vec![dir.join("first.rtf"), text.clone()],
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("notes.txt"));
+        Ok(())
     }
-    None
 }
@@ -0,0 +1,167 @@
+/// token of rtf, writing tokens back gives the source bytes except the digits of renumbered parameters
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token<'a> {
+    GroupStart,
+    GroupEnd,
+    /// control word such as `\f1`, `space` is the delimiting space which belongs to the word
+    Control {
+        name: &'a str,
+        param: Option<i64>,
+        space: bool,
+    },
+    /// control symbol such as `\~` or `\'e9`
+    Symbol(&'a [u8]),
+    /// plain text, or binary data following `\binN`
+    Text(&'a [u8]),
+}
+
+impl Token<'_> {
+    /// true if token is control word `name`
+    pub fn is(&self, word: &str) -> bool {
+        matches!(self, Token::Control { name, .. } if name.eq(&word))
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Token::GroupStart => out.push(b'{'),
+            Token::GroupEnd => out.push(b'}'),
+            Token::Control { name, param, space } => {
+                out.push(b'\\');
+                out.extend_from_slice(name.as_bytes());
+                if let Some(param) = param {
+                    out.extend_from_slice(param.to_string().as_bytes());
+                }
+                if *space {
+                    out.push(b' ');
+                }
+            }
+            Token::Symbol(raw) | Token::Text(raw) => out.extend_from_slice(raw),
+        }
+    }
+}
+
+/// split rtf into tokens
+pub fn tokenize(data: &[u8]) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut pos = 0;
+    while pos < data.len() {
+        match data[pos] {
+            b'{' => {
+                tokens.push(Token::GroupStart);
+                pos += 1;
+            }
+            b'}' => {
+                tokens.push(Token::GroupEnd);
+                pos += 1;
+            }
+            b'\\' if data.get(pos + 1).is_some_and(|c| c.is_ascii_alphabetic()) => {
+                let start = pos + 1;
+                let mut end = start;
+                while data.get(end).is_some_and(|c| c.is_ascii_alphabetic()) {
+                    end += 1;
+                }
+                let name = std::str::from_utf8(&data[start..end]).unwrap_or_default();
+                let digits = end;
+                if data.get(end).eq(&Some(&b'-')) {
+                    end += 1;
+                }
+                while data.get(end).is_some_and(|c| c.is_ascii_digit()) {
+                    end += 1;
+                }
+                let param = std::str::from_utf8(&data[digits..end])
+                    .ok()
+                    .and_then(|p| p.parse::<i64>().ok());
+                // a lone "-" is not a parameter
+                if param.is_none() {
+                    end = digits;
+                }
+                let space = data.get(end).eq(&Some(&b' '));
+                pos = if space { end + 1 } else { end };
+                tokens.push(Token::Control { name, param, space });
+                if name.eq("bin") {
+                    let size = param.unwrap_or(0).max(0) as usize;
+                    let binary_end = (pos + size).min(data.len());
+                    tokens.push(Token::Text(&data[pos..binary_end]));
+                    pos = binary_end;
+                }
+            }
+            b'\\' => {
+                let end = match data.get(pos + 1) {
+                    Some(b'\'') => pos + 4,
+                    Some(_) => pos + 2,
+                    None => pos + 1,
+                }
+                .min(data.len());
+                tokens.push(Token::Symbol(&data[pos..end]));
+                pos = end;
+            }
+            _ => {
+                let start = pos;
+                while pos < data.len() && !matches!(data[pos], b'{' | b'}' | b'\\') {
+                    pos += 1;
+                }
+                tokens.push(Token::Text(&data[start..pos]));
+            }
+        }
+    }
+    tokens
+}
+
+/// index of the token closing the group which starts at `start`, or the end of tokens if not closed
+pub fn group_end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(start) {
+        match token {
+            Token::GroupStart => depth += 1,
+            Token::GroupEnd => {
+                depth -= 1;
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+pub fn write(tokens: &[Token], out: &mut Vec<u8>) {
+    tokens.iter().for_each(|token| token.write(out));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn tokenize_test() {
+        let data = br"{\rtf1\ansi {\fonttbl{\f0\froman Times;}}\f0\fs-2 caf\'e9\~x\bin3 {}\par}";
+        let tokens = tokenize(data);
+        assert_eq!(
+            tokens[1],
+            Token::Control {
+                name: "rtf",
+                param: Some(1),
+                space: false
+            }
+        );
+        assert_eq!(
+            tokens[2],
+            Token::Control {
+                name: "ansi",
+                param: None,
+                space: true
+            }
+        );
+        assert_eq!(group_end(&tokens, 3), 10);
+        assert!(tokens.contains(&Token::Control {
+            name: "fs",
+            param: Some(-2),
+            space: true
+        }));
+        assert!(tokens.contains(&Token::Symbol(br"\'e9")));
+        assert!(tokens.contains(&Token::Text(b"{}\\")));
+        let mut out = vec![];
+        write(&tokens, &mut out);
+        assert_eq!(out, data);
+    }
+}
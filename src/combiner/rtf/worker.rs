@@ -31,7 +31,17 @@ impl RTFCombineWokrer {
                             .unwrap()
                             .send(format!("[INFO] {} rtf combine start\n", name))
                             .ok();
-                        combiner::combine(&param).unwrap();
+                        if let Err(err) = combiner::combine(&param) {
+                            logger
+                                .lock()
+                                .unwrap()
+                                .send(format!(
+                                    "[ERROR] {} rtf combine failed, because: {}\n",
+                                    name, err
+                                ))
+                                .ok();
+                            continue;
+                        }
                         status.lock().unwrap().send(()).ok();
                         logger
                             .lock()
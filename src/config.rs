@@ -1,5 +1,7 @@
 pub mod combine;
 pub mod convert;
+pub mod locale;
 pub mod param;
+pub mod pdf;
 pub mod repo;
 pub mod utils;
@@ -6,7 +6,15 @@ use std::{
 use lopdf::Document;
 use serde::Serialize;
 
-use super::utils::Language;
+use super::{
+    locale::{Catalog, LocaleSetting},
+    pdf::{
+        AttachmentSetting, CoverSetting, Divider, MetadataSetting, OptimizeSetting, OutlineSetting,
+        PDFAConformance, PageSetting, RenderBackend, SecuritySetting, SplitSetting, StampSetting,
+        TocFormat, TocLevelSetting, TocTemplate, WatermarkSetting,
+    },
+    utils::Language,
+};
 
 #[derive(Debug, Serialize)]
 pub struct OutlineParam {
@@ -27,6 +35,12 @@ pub struct PDFFile {
     pub filepath: PathBuf,
     pub page_display: usize,
     pub page_actual: usize,
+    /// rendered into `filepath` before combining
+    pub divider: Option<Divider>,
+    /// rtf which `filepath` is converted from, embedded when attachments are enabled
+    pub source: Option<PathBuf>,
+    /// heading of the group starting from this output
+    pub group: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -39,6 +53,23 @@ pub struct CombinePDFParam {
     pub(crate) files: Vec<PDFFile>,
     pub(crate) destination: PathBuf,
     pub(crate) toc_headers: (String, String, String, String),
+    pub(crate) outline: OutlineSetting,
+    pub(crate) stamp: Option<StampSetting>,
+    pub(crate) watermark: Option<WatermarkSetting>,
+    pub(crate) pdfa: Option<PDFAConformance>,
+    pub(crate) split: Option<SplitSetting>,
+    pub(crate) metadata: MetadataSetting,
+    pub(crate) optimize: Option<OptimizeSetting>,
+    pub(crate) cover_page: Option<CoverSetting>,
+    pub(crate) security: Option<SecuritySetting>,
+    pub(crate) attachments: Option<AttachmentSetting>,
+    pub(crate) page: Option<PageSetting>,
+    pub(crate) toc_backend: RenderBackend,
+    pub(crate) toc_template: TocTemplate,
+    pub(crate) toc_levels: Option<TocLevelSetting>,
+    pub(crate) toc_exports: Vec<TocFormat>,
+    /// localized strings of language with overrides of the task
+    pub(crate) catalog: Catalog,
 }
 
 #[derive(Debug, Clone)]
@@ -69,9 +100,93 @@ impl CombinePDFParam {
             files: files.to_vec(),
             destination: destination.into(),
             toc_headers: toc_headers.clone(),
+            outline: OutlineSetting::default(),
+            stamp: None,
+            watermark: None,
+            pdfa: None,
+            split: None,
+            metadata: MetadataSetting::default(),
+            optimize: None,
+            cover_page: None,
+            security: None,
+            attachments: None,
+            page: None,
+            toc_backend: Default::default(),
+            toc_template: Default::default(),
+            toc_levels: None,
+            toc_exports: vec![],
+            catalog: language.catalog(),
         })
     }
-    pub fn update_pages(&mut self) -> anyhow::Result<()> {
+    pub fn set_outline(&mut self, outline: &OutlineSetting) -> &mut Self {
+        self.outline = outline.clone();
+        self
+    }
+    pub fn set_stamp(&mut self, stamp: &Option<StampSetting>) -> &mut Self {
+        self.stamp = stamp.clone();
+        self
+    }
+    pub fn set_watermark(&mut self, watermark: &Option<WatermarkSetting>) -> &mut Self {
+        self.watermark = watermark.clone();
+        self
+    }
+    pub fn set_pdfa(&mut self, pdfa: &Option<PDFAConformance>) -> &mut Self {
+        self.pdfa = pdfa.clone();
+        self
+    }
+    pub fn set_split(&mut self, split: &Option<SplitSetting>) -> &mut Self {
+        self.split = split.clone();
+        self
+    }
+    pub fn set_metadata(&mut self, metadata: &MetadataSetting) -> &mut Self {
+        self.metadata = metadata.clone();
+        self
+    }
+    pub fn set_optimize(&mut self, optimize: &Option<OptimizeSetting>) -> &mut Self {
+        self.optimize = optimize.clone();
+        self
+    }
+    pub fn set_cover_page(&mut self, cover_page: &Option<CoverSetting>) -> &mut Self {
+        self.cover_page = cover_page.clone();
+        self
+    }
+    pub fn set_security(&mut self, security: &Option<SecuritySetting>) -> &mut Self {
+        self.security = security.clone();
+        self
+    }
+    pub fn set_attachments(&mut self, attachments: &Option<AttachmentSetting>) -> &mut Self {
+        self.attachments = attachments.clone();
+        self
+    }
+    pub fn set_page(&mut self, page: &Option<PageSetting>) -> &mut Self {
+        self.page = page.clone();
+        self
+    }
+    pub fn set_toc_backend(&mut self, toc_backend: &RenderBackend) -> &mut Self {
+        self.toc_backend = toc_backend.clone();
+        self
+    }
+    pub fn set_toc_template(&mut self, toc_template: &TocTemplate) -> &mut Self {
+        self.toc_template = toc_template.clone();
+        self
+    }
+    pub fn set_toc_levels(&mut self, toc_levels: &Option<TocLevelSetting>) -> &mut Self {
+        self.toc_levels = toc_levels.clone();
+        self
+    }
+    pub fn set_toc_exports(&mut self, toc_exports: &[TocFormat]) -> &mut Self {
+        self.toc_exports = toc_exports.to_vec();
+        self
+    }
+    pub fn set_locale(&mut self, locale: &Option<LocaleSetting>) -> &mut Self {
+        self.catalog = self.language.catalog();
+        if let Some(setting) = locale {
+            self.catalog.apply(setting);
+        }
+        self
+    }
+    /// number pages of outputs after cover and `toc_pages` pages of toc, which are counted when toc is rendered
+    pub fn update_pages(&mut self, toc_pages: usize) -> anyhow::Result<()> {
         if !self.workspace.exists() {
             fs::create_dir_all(&self.workspace)?;
         }
@@ -80,11 +195,6 @@ impl CombinePDFParam {
             Some(p) => Document::load(&p)?.get_pages().len(),
             None => 0,
         };
-        let toc_pages = if self.toc.exists() {
-            Document::load(&self.toc)?.get_pages().len()
-        } else {
-            0
-        };
         self.toc_start_pages = cover_pages;
         let mut page_display = 1;
         let mut page_actual = cover_pages + toc_pages;
@@ -102,12 +212,12 @@ impl CombinePDFParam {
         let mut locations = Vec::with_capacity(self.files.len());
         if let Some(_) = self.cover {
             locations.push(Location {
-                title: cover_title(&self.language),
+                title: self.catalog.cover_title.clone(),
                 page: 0,
             });
         }
         locations.push(Location {
-            title: toc_title(&self.language),
+            title: self.catalog.toc_title.clone(),
             page: self.toc_start_pages,
         });
         self.files.iter().for_each(|f| {
@@ -122,17 +232,3 @@ impl CombinePDFParam {
         }
     }
 }
-
-fn toc_title(lang: &Language) -> String {
-    match lang {
-        Language::CN => "目录".into(),
-        Language::EN => "Table of Content".into(),
-    }
-}
-
-fn cover_title(lang: &Language) -> String {
-    match lang {
-        Language::CN => "封面".into(),
-        Language::EN => "Cover".into(),
-    }
-}
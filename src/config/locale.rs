@@ -0,0 +1,218 @@
+use super::{pdf::PaperSize, utils::Language};
+use serde::{Deserialize, Serialize};
+
+/// localized strings and defaults of a language, overridden per task by `LocaleSetting`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Catalog {
+    pub toc_title: String,
+    pub cover_title: String,
+    /// labels of protocol number, version and date on generated cover
+    pub cover_labels: (String, String, String),
+    /// label before volume number in master index, such as "[Part 1]"
+    pub volume_label: String,
+    /// prefixes of output titles read from top, such as "Table 14.1.1: ..."
+    pub table_prefix: String,
+    pub figure_prefix: String,
+    pub listing_prefix: String,
+    /// titles of outline groups by output type
+    pub table_group: String,
+    pub figure_group: String,
+    pub listing_group: String,
+    /// title, page and file columns of exported toc
+    pub export_columns: (String, String, String),
+    /// RFC 3066 language tag, such as "en-US"
+    pub tag: String,
+    /// paper of toc and generated pages when the task has no page setting
+    pub paper: PaperSize,
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Language::default().catalog()
+    }
+}
+
+impl Catalog {
+    /// prefix of output type symbol "T", "F" or "L", empty for unknown symbols
+    pub fn prefix(&self, symbol: &str) -> &str {
+        match symbol {
+            "T" => &self.table_prefix,
+            "F" => &self.figure_prefix,
+            "L" => &self.listing_prefix,
+            _ => "",
+        }
+    }
+
+    /// outline group of output type symbol
+    pub fn group(&self, symbol: &str) -> Option<&str> {
+        match symbol {
+            "T" => Some(&self.table_group),
+            "F" => Some(&self.figure_group),
+            "L" => Some(&self.listing_group),
+            _ => None,
+        }
+    }
+
+    /// output type symbol of a title prefix
+    pub fn symbol(&self, prefix: &str) -> Option<&'static str> {
+        ["T", "F", "L"]
+            .into_iter()
+            .find(|symbol| !prefix.is_empty() && self.prefix(symbol).eq(prefix))
+    }
+
+    /// replace strings given in setting
+    pub fn apply(&mut self, setting: &LocaleSetting) -> &mut Self {
+        let fields = [
+            (&mut self.toc_title, &setting.toc_title),
+            (&mut self.cover_title, &setting.cover_title),
+            (&mut self.volume_label, &setting.volume_label),
+            (&mut self.table_prefix, &setting.table_prefix),
+            (&mut self.figure_prefix, &setting.figure_prefix),
+            (&mut self.listing_prefix, &setting.listing_prefix),
+            (&mut self.table_group, &setting.table_group),
+            (&mut self.figure_group, &setting.figure_group),
+            (&mut self.listing_group, &setting.listing_group),
+            (&mut self.tag, &setting.tag),
+        ];
+        for (field, value) in fields {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        }
+        if let Some(labels) = &setting.cover_labels {
+            self.cover_labels = labels.clone();
+        }
+        if let Some(columns) = &setting.export_columns {
+            self.export_columns = columns.clone();
+        }
+        if let Some(paper) = &setting.paper {
+            self.paper = paper.clone();
+        }
+        self
+    }
+}
+
+/// per task override of the catalog of task language, strings not given follow the language
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct LocaleSetting {
+    pub toc_title: Option<String>,
+    pub cover_title: Option<String>,
+    pub cover_labels: Option<(String, String, String)>,
+    pub volume_label: Option<String>,
+    pub table_prefix: Option<String>,
+    pub figure_prefix: Option<String>,
+    pub listing_prefix: Option<String>,
+    pub table_group: Option<String>,
+    pub figure_group: Option<String>,
+    pub listing_group: Option<String>,
+    pub export_columns: Option<(String, String, String)>,
+    pub tag: Option<String>,
+    pub paper: Option<PaperSize>,
+}
+
+impl Language {
+    pub fn all() -> [Language; 6] {
+        [
+            Language::CN,
+            Language::EN,
+            Language::JP,
+            Language::KR,
+            Language::DE,
+            Language::FR,
+        ]
+    }
+
+    /// built-in catalog of the language
+    pub fn catalog(&self) -> Catalog {
+        let (toc_title, cover_title, cover_labels, volume_label) = match self {
+            Language::CN => ("目录", "封面", ["方案编号", "版本", "日期"], "分册"),
+            Language::EN => (
+                "Table of Content",
+                "Cover",
+                ["Protocol Number", "Version", "Date"],
+                "Part",
+            ),
+            Language::JP => ("目次", "表紙", ["治験実施計画書番号", "版", "日付"], "分冊"),
+            Language::KR => ("목차", "표지", ["프로토콜 번호", "버전", "날짜"], "권"),
+            Language::DE => (
+                "Inhaltsverzeichnis",
+                "Deckblatt",
+                ["Prüfplannummer", "Version", "Datum"],
+                "Teil",
+            ),
+            Language::FR => (
+                "Table des matières",
+                "Couverture",
+                ["Numéro de protocole", "Version", "Date"],
+                "Partie",
+            ),
+        };
+        let (prefixes, groups) = match self {
+            Language::CN => (["表", "图", "列表"], ["表格", "图", "列表"]),
+            Language::EN => (
+                ["Table", "Figure", "Listing"],
+                ["Tables", "Figures", "Listings"],
+            ),
+            Language::JP => (["表", "図", "一覧"], ["表", "図", "一覧表"]),
+            Language::KR => (["표", "그림", "목록"], ["표", "그림", "목록"]),
+            Language::DE => (
+                ["Tabelle", "Abbildung", "Listing"],
+                ["Tabellen", "Abbildungen", "Listings"],
+            ),
+            Language::FR => (
+                ["Tableau", "Figure", "Listing"],
+                ["Tableaux", "Figures", "Listings"],
+            ),
+        };
+        let (columns, tag, paper) = match self {
+            Language::CN => (["标题", "页码", "文件"], "zh-CN", PaperSize::A4),
+            Language::EN => (["Title", "Page", "File"], "en-US", PaperSize::Letter),
+            Language::JP => (["タイトル", "ページ", "ファイル"], "ja-JP", PaperSize::A4),
+            Language::KR => (["제목", "페이지", "파일"], "ko-KR", PaperSize::A4),
+            Language::DE => (["Titel", "Seite", "Datei"], "de-DE", PaperSize::A4),
+            Language::FR => (["Titre", "Page", "Fichier"], "fr-FR", PaperSize::A4),
+        };
+        Catalog {
+            toc_title: toc_title.into(),
+            cover_title: cover_title.into(),
+            cover_labels: (
+                cover_labels[0].into(),
+                cover_labels[1].into(),
+                cover_labels[2].into(),
+            ),
+            volume_label: volume_label.into(),
+            table_prefix: prefixes[0].into(),
+            figure_prefix: prefixes[1].into(),
+            listing_prefix: prefixes[2].into(),
+            table_group: groups[0].into(),
+            figure_group: groups[1].into(),
+            listing_group: groups[2].into(),
+            export_columns: (columns[0].into(), columns[1].into(), columns[2].into()),
+            tag: tag.into(),
+            paper,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn catalog_test() -> anyhow::Result<()> {
+        let language: Language = serde_json::from_str(r#""EN""#)?;
+        assert_eq!(language.catalog().toc_title, "Table of Content");
+        let language: Language = serde_json::from_str(r#""JP""#)?;
+        let mut catalog = language.catalog();
+        assert_eq!(catalog.symbol("図"), Some("F"));
+        assert_eq!(catalog.paper, PaperSize::A4);
+
+        let setting: LocaleSetting =
+            serde_json::from_str(r#"{"toc_title": "目次一覧", "paper": "Letter"}"#)?;
+        catalog.apply(&setting);
+        assert_eq!(catalog.toc_title, "目次一覧");
+        assert_eq!(catalog.cover_title, "表紙");
+        assert_eq!(catalog.paper, PaperSize::Letter);
+        Ok(())
+    }
+}
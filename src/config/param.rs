@@ -6,9 +6,17 @@ use std::{
 
 use serde::{Deserialize, Serialize};
 
+use crate::qc::{check_title, TitleCheck};
+
 use super::{
     combine::{CombinePDFParam, PDFFile, RTFCombineParam},
     convert::ConvertTask,
+    locale::LocaleSetting,
+    pdf::{
+        AttachmentSetting, CoverSetting, Divider, MetadataSetting, OptimizeSetting, OutlineSetting,
+        PDFAConformance, PageSetting, RenderBackend, SecuritySetting, SplitSetting, StampSetting,
+        TocFormat, TocGroup, TocLevelSetting, TocTemplate, WatermarkSetting,
+    },
     utils::{File, FusionMode, Language},
 };
 
@@ -21,7 +29,7 @@ pub struct FusionParam {
     pub tasks: Vec<FusionTask>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct FusionTask {
     pub name: String,
     pub language: Language,
@@ -30,6 +38,58 @@ pub struct FusionTask {
     pub mode: FusionMode,
     pub files: Vec<File>,
     pub toc_headers: (String, String, String, String),
+    #[serde(default)]
+    pub outline: OutlineSetting,
+    /// not allowed together with `pdfa`, fonts of stamps are not embedded
+    #[serde(default)]
+    pub stamp: Option<StampSetting>,
+    /// not allowed together with `pdfa`, fonts of watermarks are not embedded
+    #[serde(default)]
+    pub watermark: Option<WatermarkSetting>,
+    #[serde(default)]
+    pub pdfa: Option<PDFAConformance>,
+    #[serde(default)]
+    pub split: Option<SplitSetting>,
+    #[serde(default)]
+    pub metadata: MetadataSetting,
+    #[serde(default)]
+    pub optimize: Option<OptimizeSetting>,
+    /// generate cover page when `cover` is not given or missing
+    #[serde(default)]
+    pub cover_page: Option<CoverSetting>,
+    #[serde(default)]
+    pub dividers: Vec<Divider>,
+    /// encrypt combined pdf, not allowed together with `pdfa`
+    #[serde(default)]
+    pub security: Option<SecuritySetting>,
+    /// embed source rtf files, not allowed together with `pdfa`
+    #[serde(default)]
+    pub attachments: Option<AttachmentSetting>,
+    /// paper of combined pdf, toc follows language when not given
+    #[serde(default)]
+    pub page: Option<PageSetting>,
+    /// render toc by browser from html, or natively without browser
+    #[serde(default)]
+    pub toc_backend: RenderBackend,
+    /// study, company and purpose shown in toc, and custom template of toc
+    #[serde(default)]
+    pub toc_template: TocTemplate,
+    /// headings of groups in outline and multi-level toc, without separator pages unlike `dividers`
+    #[serde(default)]
+    pub groups: Vec<TocGroup>,
+    /// indent outputs under headings in toc, otherwise a flat toc
+    #[serde(default)]
+    pub toc_levels: Option<TocLevelSetting>,
+    /// export toc as editable documents next to combined pdf
+    #[serde(default)]
+    pub toc_exports: Vec<TocFormat>,
+    /// override toc title, cover title, output prefixes and other strings of language
+    #[serde(default)]
+    pub locale: Option<LocaleSetting>,
+    /// compare titles in TOP with titles printed in outputs, report is saved as "name.title.json"
+    /// next to combined file
+    #[serde(default)]
+    pub title_qc: bool,
 }
 
 impl FusionParam {
@@ -68,6 +128,28 @@ impl FusionParam {
         file_set.len()
     }
 
+    /// compare titles in TOP with titles printed in outputs of tasks with `title_qc`, converted pdf
+    /// is preferred over rtf
+    pub fn title_qc(&self, workspace: &Path) -> Vec<(&FusionTask, Vec<TitleCheck>)> {
+        let converted = converted_pdf_dir(workspace);
+        self.tasks
+            .iter()
+            .filter(|task| task.title_qc)
+            .map(|task| {
+                let checks = task
+                    .files
+                    .iter()
+                    .map(|file| {
+                        let pdf = converted.join(file.filename.replace(".rtf", ".pdf"));
+                        let output = if pdf.exists() { pdf } else { file.path.clone() };
+                        check_title(&file.filename, &file.title, &output)
+                    })
+                    .collect();
+                (task, checks)
+            })
+            .collect()
+    }
+
     pub fn to_combine_param(
         &self,
         workspace: &Path,
@@ -91,15 +173,6 @@ impl FusionParam {
 
     pub fn fix(&mut self) -> anyhow::Result<()> {
         for (index, task) in self.tasks.clone().into_iter().enumerate() {
-            let cover = if let Some(cover) = task.cover {
-                if cover.exists() {
-                    Some(cover)
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
             let mut files = Vec::with_capacity(task.files.len());
             for file in task.files {
                 if file.path.exists() {
@@ -114,7 +187,6 @@ impl FusionParam {
             }
 
             (*self.tasks.get_mut(index).unwrap()).files = files;
-            (*self.tasks.get_mut(index).unwrap()).cover = cover;
         }
         Ok(())
     }
@@ -143,18 +215,34 @@ fn pdf_combine_task(
 ) -> anyhow::Result<CombinePDFParam> {
     let combine_workspace = workspace.join("combine").join(id.to_string());
     let toc = &combine_workspace.join("toc.pdf");
-    let mut files = Vec::with_capacity(task.files.len());
-    task.files.iter().enumerate().for_each(|(id, file)| {
+    let mut files = Vec::with_capacity(task.files.len() + task.dividers.len());
+    task.files.iter().for_each(|file| {
+        task.dividers
+            .iter()
+            .filter(|divider| divider.before.eq(&file.filename))
+            .for_each(|divider| {
+                files.push(PDFFile {
+                    id: files.len(),
+                    title: divider.title.clone(),
+                    filepath: combine_workspace.join(format!("divider-{}.pdf", files.len())),
+                    divider: Some(divider.clone()),
+                    ..Default::default()
+                });
+            });
         files.push(PDFFile {
-            id,
+            id: files.len(),
             title: file.title.clone(),
-            filepath: workspace
-                .join("converted")
-                .join(file.filename.replace(".rtf", ".pdf")),
+            filepath: converted_pdf_dir(workspace).join(file.filename.replace(".rtf", ".pdf")),
+            source: Some(file.path.clone()),
+            group: task
+                .groups
+                .iter()
+                .find(|group| group.before.eq(&file.filename))
+                .map(|group| group.title.clone()),
             ..Default::default()
         });
     });
-    let param = CombinePDFParam::new(
+    let mut param = CombinePDFParam::new(
         &combine_workspace,
         &task.language,
         &task.cover,
@@ -163,6 +251,23 @@ fn pdf_combine_task(
         &task.destination.join(format!("{}.pdf", &task.name)),
         &task.toc_headers,
     )?;
+    param
+        .set_outline(&task.outline)
+        .set_stamp(&task.stamp)
+        .set_watermark(&task.watermark)
+        .set_pdfa(&task.pdfa)
+        .set_split(&task.split)
+        .set_metadata(&task.metadata)
+        .set_optimize(&task.optimize)
+        .set_cover_page(&task.cover_page)
+        .set_security(&task.security)
+        .set_attachments(&task.attachments)
+        .set_page(&task.page)
+        .set_toc_backend(&task.toc_backend)
+        .set_toc_template(&task.toc_template)
+        .set_toc_levels(&task.toc_levels)
+        .set_toc_exports(&task.toc_exports)
+        .set_locale(&task.locale);
     Ok(param)
 }
 
@@ -181,11 +286,15 @@ fn rtf_combine_task(task: &FusionTask) -> RTFCombineParam {
 
 #[cfg(test)]
 mod tests {
+    #[cfg(windows)]
     use std::path::Path;
 
     use sha2::{Digest, Sha256};
 
+    #[cfg(windows)]
     use super::*;
+    // fixtures are on a windows drive
+    #[cfg(windows)]
     #[test]
     fn test_to_convert_task() -> anyhow::Result<()> {
         let workspace = Path::new(r"D:\Users\yuqi01.chen\.temp\app\mobiuskit\fusion");
@@ -194,6 +303,8 @@ mod tests {
         assert_eq!(tasks.len(), 5);
         Ok(())
     }
+    // fixtures are on a windows drive
+    #[cfg(windows)]
     #[test]
     fn to_combine_config() -> anyhow::Result<()> {
         let workspace = Path::new(r"D:\Users\yuqi01.chen\.temp\app\mobiuskit\fusion");
@@ -208,6 +319,8 @@ mod tests {
         Ok(())
     }
 
+    // fixtures are on a windows drive
+    #[cfg(windows)]
     #[test]
     fn param_fix_test() -> anyhow::Result<()> {
         let mut param = init();
@@ -222,6 +335,7 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(windows)]
     fn init() -> FusionParam {
         FusionParam {
             id: None,
@@ -256,6 +370,7 @@ mod tests {
                     },
                 ],
                 toc_headers: ("".into(), "".into(), "".into(), "".into()),
+                ..Default::default()
             },FusionTask {
                 name: "listing 2".into(),
                 language: Language::CN,
@@ -278,6 +393,7 @@ mod tests {
                     },
                 ],
                 toc_headers: ("".into(), "".into(), "".into(), "".into()),
+                ..Default::default()
             }],
         }
     }
@@ -0,0 +1,448 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// how the outline (bookmarks) of combined pdf is organized
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OutlineSetting {
+    /// nest outputs under sections derived from the numbering in titles, off by default for the flat
+    /// outline of earlier versions
+    pub nested: bool,
+    /// max levels of section nodes, "Table 14.2.8.3.2" nests under 14 → 14.2 → 14.2.8 when depth is 3
+    pub depth: usize,
+    /// add Tables, Figures and Listings group nodes above sections
+    pub group_by_type: bool,
+    /// headings of section nodes, section without heading is titled by its number only
+    pub headings: Vec<SectionHeading>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SectionHeading {
+    /// section number, such as "14.1"
+    pub number: String,
+    pub title: String,
+}
+
+impl Default for OutlineSetting {
+    fn default() -> Self {
+        OutlineSetting {
+            nested: false,
+            depth: 3,
+            group_by_type: false,
+            headings: vec![],
+        }
+    }
+}
+
+impl OutlineSetting {
+    /// title of section node, "14.1" => "14.1 Demographic Data" if heading is configured
+    pub fn section_title(&self, number: &str) -> String {
+        match self.headings.iter().find(|h| h.number.eq(number)) {
+            Some(heading) => format!("{} {}", number, heading.title),
+            None => number.into(),
+        }
+    }
+}
+
+/// stamp a page number line such as "Page 3 of 120" on every page of outputs in combined pdf
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct StampSetting {
+    /// "{page}" is replaced by page number counted from the first output, "{total}" by total pages of outputs
+    pub template: String,
+    pub position: StampPosition,
+    /// one of the standard 14 fonts, text with non-ascii characters always uses a predefined cjk font
+    pub font: String,
+    pub font_size: f32,
+    /// distance from the edges of page in points
+    pub margin: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+pub enum StampPosition {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomCenter,
+    BottomRight,
+}
+
+impl Default for StampSetting {
+    fn default() -> Self {
+        StampSetting {
+            template: "Page {page} of {total}".into(),
+            position: StampPosition::default(),
+            font: "Helvetica".into(),
+            font_size: 9.0,
+            margin: 20.0,
+        }
+    }
+}
+
+impl StampSetting {
+    pub fn text(&self, page: usize, total: usize) -> String {
+        self.template
+            .replace("{page}", &page.to_string())
+            .replace("{total}", &total.to_string())
+    }
+}
+
+/// diagonal text such as "DRAFT" drawn across pages of combined pdf
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct WatermarkSetting {
+    pub text: String,
+    /// from 0.0 (invisible) to 1.0 (opaque)
+    pub opacity: f32,
+    /// counterclockwise rotation in degrees around the center of page
+    pub angle: f32,
+    /// one of the standard 14 fonts, text with non-ascii characters always uses a predefined cjk font
+    pub font: String,
+    pub font_size: f32,
+    /// gray level of text, from 0.0 (black) to 1.0 (white)
+    pub gray: f32,
+    pub pages: WatermarkPages,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+pub enum WatermarkPages {
+    /// cover, toc and outputs
+    #[default]
+    All,
+    /// outputs only
+    Body,
+}
+
+impl Default for WatermarkSetting {
+    fn default() -> Self {
+        WatermarkSetting {
+            text: "DRAFT".into(),
+            opacity: 0.2,
+            angle: 45.0,
+            font: "Helvetica-Bold".into(),
+            font_size: 96.0,
+            gray: 0.5,
+            pages: WatermarkPages::default(),
+        }
+    }
+}
+
+/// split combined pdf into volumes when it exceeds limits, volumes are split at output boundaries only
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SplitSetting {
+    /// max pages of outputs in one volume
+    pub max_pages: Option<usize>,
+    /// max size of one volume in megabytes, estimated from the size of outputs
+    pub max_size: Option<f64>,
+    /// create an index pdf linking to outputs across volumes
+    pub master_index: bool,
+}
+
+impl SplitSetting {
+    pub fn max_bytes(&self) -> Option<u64> {
+        self.max_size.map(|size| (size * 1024.0 * 1024.0) as u64)
+    }
+}
+
+/// document information of combined pdf, written into both Info dictionary and XMP metadata
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct MetadataSetting {
+    /// defaults to task name
+    pub title: Option<String>,
+    pub author: String,
+    pub subject: String,
+    pub keywords: Vec<String>,
+    /// application which created the original content, defaults to this application
+    pub creator: Option<String>,
+    /// custom properties such as study ID and data cut-off date, keys are reduced to letters and digits
+    pub properties: BTreeMap<String, String>,
+}
+
+/// reduce size of combined pdf
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OptimizeSetting {
+    /// share identical fonts and images embedded by every output
+    pub deduplicate: bool,
+    /// compress streams without any filter
+    pub compress: bool,
+    /// pack objects into object streams, ignored for PDF/A-1 which disallows them
+    pub object_streams: bool,
+}
+
+impl Default for OptimizeSetting {
+    fn default() -> Self {
+        OptimizeSetting {
+            deduplicate: true,
+            compress: true,
+            object_streams: false,
+        }
+    }
+}
+
+/// cover page generated from template, used when the task has no cover pdf
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CoverSetting {
+    pub study_title: String,
+    pub protocol: String,
+    /// deliverable name, such as "Clinical Study Report Tables, Figures and Listings"
+    pub deliverable: String,
+    pub version: String,
+    /// defaults to the date of combining, such as "2024-10-01"
+    pub date: Option<String>,
+}
+
+/// separator page inserted before a group of outputs, shown as group heading in toc and outline
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Divider {
+    /// filename of the first output in the group, such as "t-14-01-01-dm.rtf"
+    pub before: String,
+    pub title: String,
+    #[serde(default)]
+    pub subtitle: Option<String>,
+}
+
+/// heading of a group of outputs in outline and multi-level toc, without separator page
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TocGroup {
+    /// filename of the first output in the group, such as "t-14-01-01-dm.rtf"
+    pub before: String,
+    pub title: String,
+}
+
+/// multi-level toc, outputs are indented under the same headings as the outline, which come from
+/// dividers, groups of the task and sections derived from numbering according to `OutlineSetting`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TocLevelSetting {
+    /// show page range of headings, such as "12-30", otherwise the first page only
+    pub page_range: bool,
+    /// indentation of every level in points
+    pub indent: f32,
+}
+
+impl Default for TocLevelSetting {
+    fn default() -> Self {
+        TocLevelSetting {
+            page_range: true,
+            indent: 12.0,
+        }
+    }
+}
+
+/// PDF/A conformance level, combined pdf is converted to conform to it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum PDFAConformance {
+    A1B,
+    A2B,
+}
+
+impl PDFAConformance {
+    /// PDF/A part and conformance level written into XMP metadata
+    pub fn identification(&self) -> (u8, String) {
+        match self {
+            PDFAConformance::A1B => (1, "B".into()),
+            PDFAConformance::A2B => (2, "B".into()),
+        }
+    }
+    /// pdf version which the PDF/A part is based on
+    pub fn version(&self) -> &str {
+        match self {
+            PDFAConformance::A1B => "1.4",
+            PDFAConformance::A2B => "1.7",
+        }
+    }
+}
+
+/// standard security handler applied to combined pdf, incompatible with PDF/A
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SecuritySetting {
+    /// password to change permissions, a random one is used when empty so permissions could not be lifted
+    pub owner_password: String,
+    /// password to open the document, empty for no open password
+    pub user_password: String,
+    pub algorithm: EncryptionAlgorithm,
+    pub permissions: Permissions,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+pub enum EncryptionAlgorithm {
+    AES128,
+    #[default]
+    AES256,
+}
+
+/// actions allowed without owner password, all disallowed by default
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Permissions {
+    pub print: bool,
+    /// copy or extract text and graphics
+    pub copy: bool,
+    /// modify contents, insert, rotate or delete pages
+    pub modify: bool,
+    /// add or modify annotations, fill form fields
+    pub annotate: bool,
+}
+
+impl Permissions {
+    /// P entry of encryption dictionary, reserved bits are set and extraction for accessibility is always allowed
+    pub fn flags(&self) -> i32 {
+        let mut flags = 0xFFFF_F0C0_u32 | 1 << 9;
+        if self.print {
+            // bit 3 print, bit 12 high quality print
+            flags |= 1 << 2 | 1 << 11;
+        }
+        if self.modify {
+            // bit 4 modify, bit 11 assemble
+            flags |= 1 << 3 | 1 << 10;
+        }
+        if self.copy {
+            flags |= 1 << 4;
+        }
+        if self.annotate {
+            // bit 6 annotate, bit 9 fill forms
+            flags |= 1 << 5 | 1 << 8;
+        }
+        flags as i32
+    }
+}
+
+/// embed source rtf of every output into combined pdf, not allowed by PDF/A
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AttachmentSetting {
+    pub placement: AttachmentPlacement,
+    /// mark entries with attachments in toc
+    pub mark_toc: bool,
+}
+
+impl Default for AttachmentSetting {
+    fn default() -> Self {
+        AttachmentSetting {
+            placement: AttachmentPlacement::Document,
+            mark_toc: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+pub enum AttachmentPlacement {
+    /// listed in attachments panel of viewer
+    #[default]
+    Document,
+    /// paperclip icon on the first page of the output
+    Annotation,
+}
+
+/// paper of combined pdf, every page is normalized to it and toc, cover and dividers are rendered in it
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PageSetting {
+    pub size: PaperSize,
+    pub orientation: PageOrientation,
+    pub policy: FitPolicy,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+pub enum PaperSize {
+    #[default]
+    A4,
+    Letter,
+}
+
+impl PaperSize {
+    /// width and height of portrait paper in points
+    pub fn dimensions(&self) -> (f32, f32) {
+        match self {
+            PaperSize::A4 => (595.28, 841.89),
+            PaperSize::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+pub enum PageOrientation {
+    #[default]
+    Landscape,
+    Portrait,
+}
+
+impl PageOrientation {
+    /// keyword of css page size
+    pub fn css(&self) -> &str {
+        match self {
+            PageOrientation::Landscape => "landscape",
+            PageOrientation::Portrait => "portrait",
+        }
+    }
+}
+
+/// how a page of other size is placed on the paper
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+pub enum FitPolicy {
+    /// scale up or down to fill the paper, keeping aspect ratio
+    #[default]
+    Fit,
+    /// keep original size and centre on the paper, pages larger than the paper are scaled down
+    Center,
+}
+
+/// how toc pages are rendered into pdf
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
+pub enum RenderBackend {
+    /// html template printed by headless chrome
+    #[default]
+    Html,
+    /// laid out directly with the TrueType font of `TocTemplate::font`, no browser is needed for toc,
+    /// cover and dividers are still printed from html
+    Native,
+}
+
+/// values of toc and an optional tera template replacing the built-in html template of toc, templates
+/// are rendered with the context below and printed by the browser, the native backend ignores them
+///
+/// - `items`: outputs with `id`, `title`, `page` (starting from 0), `divider` and `attachment`
+/// - `content`: title of toc, and `toc_headers`: four header cells repeated on every page
+/// - `study`, `company`, `purpose`: values of this setting
+/// - `language`: such as "CN" or "EN", `date`: date of combining, such as "2024-10-01"
+/// - `total_pages`: pages of outputs listed in toc
+/// - `size`, `orientation`, `page_width` and `page_height`: paper, width and height in points
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TocTemplate {
+    pub path: Option<PathBuf>,
+    pub study: String,
+    pub company: String,
+    pub purpose: String,
+    /// TrueType font (.ttf or .ttc) embedded by the native backend, required by it and covering the
+    /// characters of titles
+    pub font: Option<PathBuf>,
+}
+
+/// format of toc exported next to combined pdf, such as "name_toc.docx"
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum TocFormat {
+    RTF,
+    DOCX,
+    XLSX,
+    /// self-contained html without external resources
+    HTML,
+}
+
+impl TocFormat {
+    pub fn extension(&self) -> &str {
+        match self {
+            TocFormat::RTF => "rtf",
+            TocFormat::DOCX => "docx",
+            TocFormat::XLSX => "xlsx",
+            TocFormat::HTML => "html",
+        }
+    }
+}
@@ -159,8 +159,11 @@ mod tests {
     use super::*;
     #[test]
     fn config_test() {
-        let root = Path::new(r"D:\Users\yuqi01.chen\.temp\app\mobiuskit\fusion");
-        let mut config = ConfigManager::new(root);
+        let root = std::env::temp_dir().join("fusion_config_repo");
+        if root.exists() {
+            std::fs::remove_dir_all(&root).unwrap();
+        }
+        let mut config = ConfigManager::new(&root);
         config
             .save_config(
                 &&SaveConfigParam {
@@ -259,6 +262,7 @@ mod tests {
                     },
                 ],
                 toc_headers: ("".into(), "".into(), "".into(), "".into()),
+                ..Default::default()
             }, FusionTask {
                 name: "all_listings".into(),
                 language: Language::CN,
@@ -305,6 +309,7 @@ mod tests {
                     },
                 ],
                 toc_headers: ("".into(), "".into(), "".into(), "".into()),
+                ..Default::default()
             }],
         }
     }
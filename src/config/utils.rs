@@ -5,14 +5,20 @@ use std::{
 };
 
 const WORKER_NUMBER_ENV: &str = "MK_WORD_WORKER";
-const COMBINE_BIN: &str = "MK_COMBINE_BIN";
 const APP_ROOT: &str = "MK_FUSION";
+const SOFFICE_ENV: &str = "MK_SOFFICE";
+const BROWSER_TABS_ENV: &str = "MK_BROWSER_TABS";
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+/// language of a task, localized strings are in `Language::catalog`
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
 pub enum Language {
     #[default]
     CN,
     EN,
+    JP,
+    KR,
+    DE,
+    FR,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq)]
@@ -41,13 +47,23 @@ pub fn worker_number() -> usize {
     }
 }
 
-pub fn combiner_bin() -> Option<PathBuf> {
-    match env::var(COMBINE_BIN) {
-        Ok(bin) => Some(Path::new(&bin).into()),
-        Err(_) => None,
+/// tabs printing html at the same time in the browser shared by a fusion run
+pub fn browser_tabs() -> usize {
+    let default_tabs = 4;
+    match env::var(BROWSER_TABS_ENV) {
+        Ok(tabs) => match tabs.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => default_tabs,
+        },
+        Err(_) => default_tabs,
     }
 }
 
+/// libreoffice executable converting rtf on platforms other than windows
+pub fn soffice_bin() -> String {
+    env::var(SOFFICE_ENV).unwrap_or("soffice".into())
+}
+
 pub fn workspace(id: Option<String>) -> anyhow::Result<PathBuf> {
     let mut is_temp = false;
     let root = fusion_app_root()?.join("workspace");
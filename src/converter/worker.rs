@@ -1,11 +1,10 @@
-use rtf2pdf::rtf2pdf;
 use std::{
     sync::{mpsc, Arc, Mutex},
     thread,
     time::Duration,
 };
 
-use crate::config::convert::ConvertTask;
+use crate::{config::convert::ConvertTask, platform::rtf_to_pdf};
 
 pub struct Worker {
     haneler: Option<thread::JoinHandle<()>>,
@@ -50,7 +49,13 @@ impl Worker {
                             .unwrap()
                             .send(format!("[INFO] {} convert start\n", &task_name))
                             .ok();
-                        rtf2pdf(vec![(source, destination)], &task.script).ok();
+                        if let Err(e) = rtf_to_pdf(&source, &destination, &task.script) {
+                            logger
+                                .lock()
+                                .unwrap()
+                                .send(format!("[ERROR] {} convert failed: {}\n", &task_name, e))
+                                .ok();
+                        }
                         status.lock().unwrap().send(()).unwrap();
                         logger
                             .lock()
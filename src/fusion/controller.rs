@@ -4,12 +4,14 @@ use crate::{
         combine::{CombinePDFParam, RTFCombineParam},
         convert::ConvertTask,
         param::FusionParam,
-        utils::{combiner_bin, worker_number},
+        utils::{browser_tabs, worker_number},
     },
     converter::controller::ConvertController,
+    qc::TitleStatus,
 };
 use std::{
     fs,
+    path::Path,
     sync::{
         mpsc::{self, Sender},
         Arc, Mutex,
@@ -62,6 +64,42 @@ impl FusionController {
         Ok(())
     }
 
+    /// compare titles in TOP with titles printed in outputs after conversion, report of each task
+    /// is saved as "name.title.json" next to combined file
+    pub fn title_qc(
+        &self,
+        param: &FusionParam,
+        workspace: &Path,
+        logger: Arc<Mutex<Sender<String>>>,
+    ) -> anyhow::Result<()> {
+        for (task, checks) in param.title_qc(workspace) {
+            if !task.destination.exists() {
+                fs::create_dir_all(&task.destination)?;
+            }
+            fs::write(
+                task.destination.join(format!("{}.title.json", task.name)),
+                serde_json::to_string_pretty(&checks)?,
+            )?;
+            let failures = checks
+                .iter()
+                .filter(|check| check.status.ne(&TitleStatus::Matched))
+                .count();
+            if failures.gt(&0) {
+                logger
+                    .lock()
+                    .unwrap()
+                    .send(format!(
+                        "[WARN] {} title qc failed, {} of {} titles not matched\n",
+                        task.name,
+                        failures,
+                        checks.len()
+                    ))
+                    .ok();
+            }
+        }
+        Ok(())
+    }
+
     /// combine outputs
     pub fn combine(
         &self,
@@ -70,8 +108,6 @@ impl FusionController {
         status: Arc<Mutex<Sender<()>>>,
         logger: Arc<Mutex<Sender<String>>>,
     ) -> anyhow::Result<()> {
-        let combiner_bin = combiner_bin().expect("Error: invalid binary combiner executor");
-
         let max_workers = worker_number();
         let pdf_tasks = pdf_configs.len();
         let rtf_tasks = rtf_configs.len();
@@ -82,9 +118,9 @@ impl FusionController {
                 } else {
                     pdf_tasks
                 },
+                browser_tabs(),
                 Arc::clone(&status),
                 Arc::clone(&logger),
-                &combiner_bin,
             );
             pdf_controller.combine(pdf_configs);
         }
@@ -61,14 +61,14 @@ mod tests {
     use super::*;
     #[test]
     fn test_logger() -> anyhow::Result<()> {
-        let log_path = Path::new(r"D:\Studies\ak112\303\stats\CSR\product\output\combined\log.txt");
+        let log_path = std::env::temp_dir().join("fusion_logger_test.txt");
         if log_path.exists() {
-            fs::remove_file(log_path)?;
+            fs::remove_file(&log_path)?;
         }
         let mut handlers = vec![];
         let (tx, rx) = mpsc::channel();
         let tx = Arc::new(Mutex::new(tx));
-        let logger = Logger::new(rx, log_path)?;
+        let logger = Logger::new(rx, &log_path)?;
         handlers.push(thread::spawn(move || {
             for _ in 0..10 {
                 let message = logger.read().unwrap();
@@ -2,4 +2,6 @@ pub mod combiner;
 pub mod config;
 pub mod converter;
 pub mod fusion;
+pub mod platform;
+pub mod qc;
 pub mod top;
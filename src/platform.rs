@@ -0,0 +1,162 @@
+use headless_chrome::{Browser, LaunchOptions};
+use std::{ffi::OsStr, path::Path, process::Command};
+
+/// command without console window on windows
+pub fn command<S: AsRef<OsStr>>(program: S) -> Command {
+    #[allow(unused_mut)]
+    let mut command = Command::new(program);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    command
+}
+
+/// file url of absolute path, `file:///C:/a/b.html` on windows and `file:///a/b.html` elsewhere
+pub fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    // verbatim prefix is added by canonicalize on windows
+    let path = path.strip_prefix(r"\\?\").unwrap_or(&path);
+    let path = if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    };
+    let encoded = path
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect::<String>();
+    format!("file:///{}", encoded.trim_start_matches('/'))
+}
+
+/// export rtf as pdf by word, run by powershell on windows
+#[cfg(windows)]
+const WORD_SCRIPT: &str = r#"param([string]$Source, [string]$Destination)
+$word = New-Object -ComObject Word.Application
+$word.Visible = $false
+$word.DisplayAlerts = 0
+try {
+    $document = $word.Documents.Open($Source, $false, $true)
+    $document.ExportAsFixedFormat($Destination, 17)
+    $document.Close(0)
+} finally {
+    $word.Quit()
+    [void][System.Runtime.InteropServices.Marshal]::ReleaseComObject($word)
+}
+"#;
+
+/// convert rtf to pdf by word on windows, the script is written under script directory
+#[cfg(windows)]
+pub fn rtf_to_pdf(source: &Path, destination: &Path, script: &Path) -> anyhow::Result<()> {
+    let stem = source
+        .file_stem()
+        .ok_or(anyhow::anyhow!("invalid source {}", source.display()))?
+        .to_string_lossy()
+        .to_string();
+    let script = script.join(format!("rtf2pdf-{}.ps1", stem));
+    std::fs::write(&script, WORD_SCRIPT)?;
+    let output = command("powershell")
+        .args([
+            "-NoProfile",
+            "-NonInteractive",
+            "-ExecutionPolicy",
+            "Bypass",
+            "-File",
+        ])
+        .arg(&script)
+        .arg(std::path::absolute(source)?)
+        .arg(std::path::absolute(destination)?)
+        .output()?;
+    std::fs::remove_file(&script).ok();
+    if !output.status.success() || !destination.exists() {
+        anyhow::bail!(
+            "word failed to convert {}: {}",
+            source.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// convert rtf to pdf by libreoffice, each conversion uses its own profile under script directory,
+/// so that workers do not lock each other
+#[cfg(not(windows))]
+pub fn rtf_to_pdf(source: &Path, destination: &Path, script: &Path) -> anyhow::Result<()> {
+    let stem = source
+        .file_stem()
+        .ok_or(anyhow::anyhow!("invalid source {}", source.display()))?
+        .to_string_lossy()
+        .to_string();
+    let profile = script.join(format!("soffice-{}", stem));
+    let outdir = script.join(format!("output-{}", stem));
+    std::fs::create_dir_all(&outdir)?;
+    let output = command(crate::config::utils::soffice_bin())
+        .arg(format!("-env:UserInstallation={}", file_url(&profile)))
+        .args(["--headless", "--convert-to", "pdf", "--outdir"])
+        .arg(&outdir)
+        .arg(source)
+        .output()?;
+    let converted = outdir.join(format!("{}.pdf", stem));
+    if !output.status.success() || !converted.exists() {
+        anyhow::bail!(
+            "libreoffice failed to convert {}: {}",
+            source.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    std::fs::copy(&converted, destination)?;
+    std::fs::remove_dir_all(&outdir).ok();
+    std::fs::remove_dir_all(&profile).ok();
+    Ok(())
+}
+
+/// headless chrome, sandbox is disabled when running as root on linux since chrome refuses to start
+pub fn browser() -> anyhow::Result<Browser> {
+    let options = LaunchOptions::default_builder()
+        .sandbox(!is_root())
+        .build()?;
+    Browser::new(options)
+}
+
+#[cfg(target_os = "linux")]
+fn is_root() -> bool {
+    std::fs::read_to_string("/proc/self/status")
+        .map(|status| {
+            status
+                .lines()
+                .find(|line| line.starts_with("Uid:"))
+                .and_then(|line| line.split_whitespace().nth(1))
+                == Some("0")
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_root() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn file_url_test() {
+        #[cfg(not(windows))]
+        assert_eq!(
+            file_url(Path::new("/tmp/fusion work/toc.html")),
+            "file:///tmp/fusion%20work/toc.html"
+        );
+        #[cfg(windows)]
+        assert_eq!(
+            file_url(Path::new(r"C:\fusion work\toc.html")),
+            "file:///C:/fusion%20work/toc.html"
+        );
+    }
+}
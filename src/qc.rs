@@ -0,0 +1,300 @@
+use crate::top::handle_unicode_declaration;
+use encoding_rs::{Encoding, BIG5, EUC_KR, GBK, SHIFT_JIS, WINDOWS_1252};
+use lopdf::Document;
+use serde::Serialize;
+use std::{fs, path::Path};
+
+/// max lines a title wraps into
+const TITLE_MAX_LINES: usize = 4;
+/// rtf destinations without visible text
+const HIDDEN_DESTINATIONS: [&str; 7] = [
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "listtable",
+    "listoverridetable",
+];
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub enum TitleStatus {
+    Matched,
+    Mismatched,
+    /// no line starts with the output number of expected title
+    NotFound,
+    /// output could not be read
+    Unreadable,
+}
+
+/// result of comparing title in TOP with title printed in output
+#[derive(Debug, Serialize, Clone)]
+pub struct TitleCheck {
+    pub filename: String,
+    pub expected: String,
+    pub found: Option<String>,
+    pub status: TitleStatus,
+}
+
+/// compare title with the one printed in output, output is a pdf converted from rtf or the rtf itself
+pub fn check_title(filename: &str, expected: &str, output: &Path) -> TitleCheck {
+    let expected = normalize_title(expected);
+    let text = match output.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("pdf") => pdf_text(output),
+        _ => fs::read(output)
+            .map_err(anyhow::Error::from)
+            .map(|data| rtf_text(&data)),
+    };
+    let (found, status) = match text {
+        Ok(text) => match find_title(&text, &expected) {
+            Some(found) if compact(&found).eq(&compact(&expected)) => {
+                (Some(found), TitleStatus::Matched)
+            }
+            Some(found) => (Some(found), TitleStatus::Mismatched),
+            None => (None, TitleStatus::NotFound),
+        },
+        Err(_) => (None, TitleStatus::Unreadable),
+    };
+    TitleCheck {
+        filename: filename.into(),
+        expected,
+        found,
+        status,
+    }
+}
+
+/// replace unicode declarations and collapse whitespaces, same as titles read from TOP
+pub fn normalize_title(title: &str) -> String {
+    handle_unicode_declaration(title)
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// title lines start from the line with the output number, such as "Table 14.1.1", and wrap
+/// until the text is as long as the expected title or an empty line is met
+fn find_title(text: &str, expected: &str) -> Option<String> {
+    let key = compact(expected.split(':').next().unwrap_or(expected));
+    let lines = text.lines().map(normalize_title).collect::<Vec<String>>();
+    let start = lines
+        .iter()
+        .position(|line| compact(line).starts_with(&key))?;
+    let expected_length = compact(expected).chars().count();
+    let mut title = vec![];
+    for line in lines.iter().skip(start).take(TITLE_MAX_LINES) {
+        if line.is_empty() {
+            break;
+        }
+        title.push(line.as_str());
+        if compact(&title.join("")).chars().count() >= expected_length {
+            break;
+        }
+    }
+    Some(title.join(" "))
+}
+
+fn compact(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// text of the first page
+fn pdf_text(path: &Path) -> anyhow::Result<String> {
+    Ok(Document::load(path)?.extract_text(&[1])?)
+}
+
+/// visible text of rtf, paragraphs, rows and cells are separated by line breaks, "\'XX" are
+/// decoded by code page of "\ansicpg", such as GBK of chinese outputs
+fn rtf_text(data: &[u8]) -> String {
+    let mut text = String::new();
+    let mut encoding = WINDOWS_1252;
+    // bytes of "\'XX", decoded together since characters of double-byte code pages take two
+    let mut bytes = vec![];
+    // depth of groups, and depth at which a hidden destination starts
+    let mut depth = 0;
+    let mut hidden: Option<usize> = None;
+    // "\ucN" of each group, and fallback characters left to skip after "\uN"
+    let mut uc = vec![1];
+    let mut skip = 0;
+    let mut index = 0;
+    while index < data.len() {
+        let byte = data[index];
+        index += 1;
+        match byte {
+            b'{' => {
+                decode(&mut text, &mut bytes, encoding);
+                depth += 1;
+                uc.push(uc.last().copied().unwrap_or(1));
+            }
+            b'}' => {
+                decode(&mut text, &mut bytes, encoding);
+                if hidden == Some(depth) {
+                    hidden = None;
+                }
+                depth = depth.saturating_sub(1);
+                if uc.len() > 1 {
+                    uc.pop();
+                }
+            }
+            b'\\' => {
+                let start = index;
+                while index < data.len() && data[index].is_ascii_alphabetic() {
+                    index += 1;
+                }
+                let word = String::from_utf8_lossy(&data[start..index]).to_string();
+                if word.is_empty() {
+                    // control symbol
+                    let symbol = data.get(index).copied().unwrap_or(b' ');
+                    index += 1;
+                    if symbol == b'\'' {
+                        let hex =
+                            String::from_utf8_lossy(data.get(index..index + 2).unwrap_or(b""))
+                                .to_string();
+                        index += 2;
+                        if skip > 0 {
+                            skip -= 1;
+                        } else if hidden.is_none() {
+                            if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                                bytes.push(byte);
+                            }
+                        }
+                        continue;
+                    }
+                    decode(&mut text, &mut bytes, encoding);
+                    let character = match symbol {
+                        b'*' => {
+                            hidden.get_or_insert(depth);
+                            None
+                        }
+                        b'~' => Some(' '),
+                        b'{' | b'}' | b'\\' => Some(symbol as char),
+                        _ => None,
+                    };
+                    if let Some(character) = character {
+                        if skip > 0 {
+                            skip -= 1;
+                        } else if hidden.is_none() {
+                            text.push(character);
+                        }
+                    }
+                    continue;
+                }
+                decode(&mut text, &mut bytes, encoding);
+                let param_start = index;
+                if index < data.len() && data[index] == b'-' {
+                    index += 1;
+                }
+                while index < data.len() && data[index].is_ascii_digit() {
+                    index += 1;
+                }
+                let param = String::from_utf8_lossy(&data[param_start..index])
+                    .parse::<i32>()
+                    .ok();
+                if index < data.len() && data[index] == b' ' {
+                    index += 1;
+                }
+                match word.as_str() {
+                    "ansicpg" => encoding = code_page(param.unwrap_or(1252)),
+                    "uc" => {
+                        if let Some(count) = uc.last_mut() {
+                            *count = param.unwrap_or(1).max(0) as usize;
+                        }
+                    }
+                    _ => {}
+                }
+                if HIDDEN_DESTINATIONS.contains(&word.as_str()) {
+                    hidden.get_or_insert(depth);
+                }
+                if hidden.is_some() {
+                    continue;
+                }
+                match word.as_str() {
+                    "par" | "line" | "row" | "cell" | "page" | "sect" => text.push('\n'),
+                    "tab" => text.push(' '),
+                    "u" => {
+                        // negative values are used for code points above 32767
+                        let code = param.unwrap_or(0);
+                        let code = if code < 0 { code + 65536 } else { code };
+                        if let Some(character) = char::from_u32(code as u32) {
+                            text.push(character);
+                        }
+                        skip = uc.last().copied().unwrap_or(1);
+                    }
+                    _ => {}
+                }
+            }
+            b'\r' | b'\n' => {}
+            _ => {
+                if skip > 0 {
+                    skip -= 1;
+                } else if hidden.is_none() {
+                    bytes.push(byte);
+                    if byte.is_ascii() {
+                        decode(&mut text, &mut bytes, encoding);
+                    }
+                }
+            }
+        }
+    }
+    decode(&mut text, &mut bytes, encoding);
+    text
+}
+
+/// decode bytes collected from rtf and append to text
+fn decode(text: &mut String, bytes: &mut Vec<u8>, encoding: &'static Encoding) {
+    if !bytes.is_empty() {
+        text.push_str(&encoding.decode_without_bom_handling(bytes).0);
+        bytes.clear();
+    }
+}
+
+/// encoding of windows code page in "\ansicpgN"
+fn code_page(code_page: i32) -> &'static Encoding {
+    match code_page {
+        936 => GBK,
+        950 => BIG5,
+        932 => SHIFT_JIS,
+        949 => EUC_KR,
+        _ => {
+            Encoding::for_label(format!("windows-{}", code_page).as_bytes()).unwrap_or(WINDOWS_1252)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn rtf_text_test() {
+        let rtf = br"{\rtf1{\fonttbl{\f1 SimSun;}}{\header\pard Table 14.1.1: Demographic\line and Baseline\cell}\pard \u34920\'3f2\par}";
+        let text = rtf_text(rtf);
+        assert_eq!(text, "Table 14.1.1: Demographic\nand Baseline\n\u{8868}2\n");
+    }
+
+    #[test]
+    fn rtf_text_cn_test() {
+        let rtf = br"{\rtf1\ansi\ansicpg936\deff0{\fonttbl{\f0\fnil\fcharset134 SimSun;}}{\header\pard \'b1\'ed 14.1.1: \'c8\'cb\'bf\'da\'d1\'a7\line \'bb\'f9\'cf\'df\cell}\pard{\uc2\u34920\'b1\'ed}2\uc0\u8805 65\par}";
+        let text = rtf_text(rtf);
+        assert_eq!(text, "表 14.1.1: 人口学\n基线\n表2≥65\n");
+        let path = std::env::temp_dir().join("fusion-qc-cn.rtf");
+        fs::write(&path, rtf).unwrap();
+        let check = check_title("t-14-01-01.rtf", "表 14.1.1: 人口学基线", &path);
+        assert_eq!(check.found, Some("表 14.1.1: 人口学 基线".into()));
+        assert_eq!(check.status, TitleStatus::Matched);
+    }
+
+    #[test]
+    fn check_title_test() {
+        let expected =
+            normalize_title("Table 14.1.1:  Demographic and Baseline ~{unicode 2265} 65");
+        assert_eq!(expected, "Table 14.1.1: Demographic and Baseline ≥ 65");
+        let text = "Study AK112-303\nTable 14.1.1: Demographic\nand Baseline ≥ 65\n\nPopulation";
+        let found = find_title(text, &expected).unwrap();
+        assert_eq!(compact(&found), compact(&expected));
+        let text = "Table 14.1.1: Demographic Summary\n";
+        assert_eq!(
+            find_title(text, &expected),
+            Some("Table 14.1.1: Demographic Summary".into())
+        );
+        assert_eq!(find_title("Table 14.1.2: Other", &expected), None);
+    }
+}
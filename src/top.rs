@@ -1,3 +1,4 @@
+use crate::config::{locale::Catalog, utils::Language};
 use calamine::{open_workbook, DataType, Reader, Xlsx};
 use once_cell::sync::Lazy;
 use regex::{Captures, Regex};
@@ -16,21 +17,8 @@ pub struct Top {
     pub title: String,
 }
 
-#[derive(Debug)]
-enum ProjectLanguage {
-    EN,
-    CN,
-}
-
-fn top_language(title: &str) -> ProjectLanguage {
-    if title.chars().any(|c| c > '\u{7F}') {
-        ProjectLanguage::CN
-    } else {
-        ProjectLanguage::EN
-    }
-}
-
-pub fn read_top(file: &Path) -> anyhow::Result<Vec<Top>> {
+/// outputs listed in TOP, titles are prefixed by output type in `catalog`, such as "Table 14.1.1: xxx"
+pub fn read_top(file: &Path, catalog: &Catalog) -> anyhow::Result<Vec<Top>> {
     let mut result = vec![];
     let mut workbook: Xlsx<_> = open_workbook(file)?;
     if let Some(range) = workbook.worksheet_range_at(0) {
@@ -66,7 +54,7 @@ pub fn read_top(file: &Path) -> anyhow::Result<Vec<Top>> {
                     filename: format!("{}.rtf", &name.unwrap()),
                     title: format!(
                         "{} {}: {}",
-                        title_prefix(&output_type.unwrap(), &top_language(&title)),
+                        catalog.prefix(&output_type.unwrap()),
                         number.unwrap(),
                         title,
                     )
@@ -79,28 +67,32 @@ pub fn read_top(file: &Path) -> anyhow::Result<Vec<Top>> {
     Ok(result)
 }
 
-fn title_prefix(symbol: &str, language: &ProjectLanguage) -> String {
-    match symbol {
-        "T" => match language {
-            ProjectLanguage::EN => "Table".into(),
-            ProjectLanguage::CN => "表".into(),
-        },
-        "F" => match language {
-            ProjectLanguage::EN => "Figure".into(),
-            ProjectLanguage::CN => "图".into(),
-        },
-        "L" => match language {
-            ProjectLanguage::EN => "Listing".into(),
-            ProjectLanguage::CN => "列表".into(),
-        },
-        _ => "".into(),
-    }
+/// split title created by `read_top` into output type symbol and output number,
+/// for example: "Table 14.2.8.3.2: xxx" => ("T", "14.2.8.3.2")
+///
+/// symbol is empty if the prefix is not a known output type, prefixes of `catalog` are tried before
+/// prefixes of all languages
+pub fn title_number(title: &str, catalog: Option<&Catalog>) -> Option<(String, String)> {
+    static PATTERN: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^(?:(\S+)\s+)?(\d+(?:\.\d+)*)\s*:").unwrap());
+    let cap = PATTERN.captures(title.trim())?;
+    let prefix = cap.get(1).map_or("", |m| m.as_str());
+    let number = cap.get(2)?.as_str().to_string();
+    let symbol = catalog
+        .and_then(|catalog| catalog.symbol(prefix))
+        .or_else(|| {
+            Language::all()
+                .iter()
+                .find_map(|language| language.catalog().symbol(prefix))
+        })
+        .unwrap_or("");
+    Some((symbol.into(), number))
 }
 
 /// handle unicode decalration in title, such as "PT Rate ≥ 5~{unicode 0025}", "~{unicode 0025}" stands for "%",
 ///
 /// unicode declaration using hex code
-fn handle_unicode_declaration(source: &str) -> String {
+pub(crate) fn handle_unicode_declaration(source: &str) -> String {
     static PATTERN: Lazy<Regex> =
         Lazy::new(|| Regex::new(r"~\{unicode\s([A-Za-z0-9]{4})}").unwrap());
     let replaced = PATTERN.replace_all(source, |cap: &Captures| {
@@ -127,17 +119,68 @@ fn unicode_convert(source: &str) -> String {
 
 #[cfg(test)]
 mod tests {
+    use crate::config::locale::LocaleSetting;
+
     use super::*;
+    #[cfg(windows)]
     #[test]
     fn read_top_test() -> anyhow::Result<()> {
         let filepath = Path::new(r"D:\Studies\ak112\303\stats\CSR\utility\top-ak112-303-CSR.xlsx");
-        let top = read_top(filepath)?;
+        let top = read_top(filepath, &Language::CN.catalog())?;
         assert!(!top.is_empty());
         top.iter().for_each(|e| {
             println!("{:?}", e);
         });
         Ok(())
     }
+    #[test]
+    fn read_top_catalog_test() -> anyhow::Result<()> {
+        let filepath = std::env::temp_dir().join("fusion-top-catalog.xlsx");
+        let mut workbook = rust_xlsxwriter::Workbook::new();
+        let sheet = workbook.add_worksheet();
+        // columns before title are section, sequence, type, program, name and number
+        let rows = [
+            [
+                "Section", "No", "Type", "Program", "Name", "Number", "Title",
+            ],
+            [
+                "14.1",
+                "1",
+                "T",
+                "t_dm",
+                "T-14-01-01",
+                "14.1.1",
+                "Demographie",
+            ],
+            [
+                "16.2",
+                "2",
+                "L",
+                "l_ds",
+                "l-16-02-01",
+                "16.2.1",
+                "Abbruch ~{unicode 2265} 1",
+            ],
+        ];
+        for (row, values) in rows.iter().enumerate() {
+            for (column, value) in values.iter().enumerate() {
+                sheet.write_string(row as u32, column as u16, *value)?;
+            }
+        }
+        workbook.save(&filepath)?;
+        let mut catalog = Language::DE.catalog();
+        catalog.apply(&LocaleSetting {
+            table_prefix: Some("Tab.".into()),
+            ..Default::default()
+        });
+        let top = read_top(&filepath, &catalog)?;
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].filename, "t-14-01-01.rtf");
+        assert_eq!(top[0].title, "Tab. 14.1.1: Demographie");
+        assert_eq!(top[1].title, "Listing 16.2.1: Abbruch ≥ 1");
+        Ok(())
+    }
+
     #[test]
     fn handle_unicode_declaration_test() {
         let source = "表 3.1.2.2.3: 整体治疗阶段的TEAE按SOC、PT总结（任意一组别PT发生率 ≥ 1~{unicode 00B5}）（安全性分析集）";
@@ -145,6 +188,29 @@ mod tests {
         assert_eq!("表 3.1.2.2.3: 整体治疗阶段的TEAE按SOC、PT总结（任意一组别PT发生率 ≥ 1µ）（安全性分析集）", dest)
     }
 
+    #[test]
+    fn title_number_test() {
+        assert_eq!(
+            title_number("Table 14.2.8.3.2: EQ-5D-5L Index", None),
+            Some(("T".into(), "14.2.8.3.2".into()))
+        );
+        assert_eq!(
+            title_number("列表 16.2.4.4: 既往病史 - 全分析集", None),
+            Some(("L".into(), "16.2.4.4".into()))
+        );
+        assert_eq!(
+            title_number("Abbildung 14.1: Disposition", None),
+            Some(("F".into(), "14.1".into()))
+        );
+        let mut catalog = Language::EN.catalog();
+        catalog.table_prefix = "Tbl".into();
+        assert_eq!(
+            title_number("Tbl 14.1: Demographic", Some(&catalog)),
+            Some(("T".into(), "14.1".into()))
+        );
+        assert_eq!(title_number("Large size output 0", None), None);
+    }
+
     #[test]
     fn unicode_convert_test() {
         let source = "0025";
@@ -1,3 +1,6 @@
+// fixtures are on a windows drive
+#![cfg(windows)]
+
 use std::{
     env,
     path::Path,
@@ -67,6 +70,9 @@ fn fusion_integration_test() -> anyhow::Result<()> {
             phase = combine_stage_notifier.wait(phase).unwrap();
             *phase = true;
         }
+        controller
+            .title_qc(&param, &workspace, Arc::clone(&log_tx))
+            .ok();
         println!("[RUNNER] Combine start");
         controller
             .combine(
@@ -155,6 +161,8 @@ fn param() -> FusionParam {
                 },
             ],
             toc_headers: ("".into(), "".into(), "".into(), "".into()),
+            title_qc: true,
+            ..Default::default()
         }, FusionTask {
             name: "all_listings".into(),
             language: Language::CN,
@@ -201,16 +209,15 @@ fn param() -> FusionParam {
                 },
             ],
             toc_headers: ("".into(), "".into(), "".into(), "".into()),
+            ..Default::default()
         }],
     }
 }
 
 fn env_prepare() -> anyhow::Result<()> {
     const WORKER_NUMBER_ENV: &str = "MK_WORD_WORKER";
-    const OUTLINE_BIN: &str = "MK_OUTLINE_BIN";
     const APP_ROOT: &str = "MK_FUSION";
     env::set_var(WORKER_NUMBER_ENV, 5.to_string());
-    env::set_var(OUTLINE_BIN, r"D:\projects\py\outlines\dist\outline.exe");
     env::set_var(APP_ROOT, r"D:\Users\yuqi01.chen\.temp\app\mobiuskit\fusion");
     Ok(())
 }